@@ -1,90 +1,451 @@
 use crate::{
+    conveyor::OnConveyor,
     enemies::{move_enemies, Enemy},
     gold::{Gold, MouseFollow},
+    hexes::past_board_edge,
+    spatial::{SpatialGrid, SpatialIndex},
+    transfer::Courier,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, time::FixedTimestep};
 use rand::prelude::*;
 
+const SEPARATION_DIST: f32 = 10.0;
+// boids always step this much, however fast the frames are
+const BOID_STEP: f32 = 1.0 / 60.0;
+// how quickly the measured heading follows actual movement
+const HEADING_SMOOTHING: f32 = 0.2;
+// gold starts getting pushed back this far inside the edge of the board
+const BOARD_INSET: f32 = 15.0;
+// the index is built once a frame, boids can have moved a little since
+const INDEX_SLACK: f32 = 5.0;
+
 pub struct BoidsPlugin;
 
 impl Plugin for BoidsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(boids_gold)
-            .add_system(boids_enemy)
-            .add_system(move_boids.before(move_enemies));
+        app.insert_resource(BoidTuning::default())
+            .add_system(give_params)
+            .add_system(measure_headings.after(give_params))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(BOID_STEP as f64))
+                    .after(measure_headings)
+                    .before(move_enemies)
+                    .with_system(boids_gold)
+                    .with_system(boids_enemy)
+                    .with_system(gold_bounds)
+                    .with_system(hold_carried)
+                    .with_system(
+                        move_boids
+                            .after(boids_gold)
+                            .after(boids_enemy)
+                            .after(gold_bounds)
+                            .after(hold_carried),
+                    ),
+            );
+        // tuning keys are only for working on the game
+        if cfg!(debug_assertions) {
+            app.add_startup_system(spawn_tuning_label)
+                .add_system(tune_boids.before(give_params))
+                .add_system(show_tuning.after(tune_boids));
+        }
     }
 }
 
 #[derive(Component)]
 pub struct Boid {
-    sep_dir: Vec2,
+    // where the flock wants it to go, rebuilt every step
+    force: Vec2,
+    velocity: Vec2,
+    // how it has actually been moving, used for alignment
+    heading: Vec2,
+    last_pos: Option<Vec2>,
 }
 
 impl Boid {
     pub fn new() -> Self {
         Boid {
-            sep_dir: Vec2::ZERO,
+            force: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            heading: Vec2::ZERO,
+            last_pos: None,
         }
     }
+
+    // measured, so it includes movement from outside the flock
+    pub fn heading(&self) -> Vec2 {
+        self.heading
+    }
 }
 
-fn boids_gold(mut q_gold: Query<(&Transform, &mut Boid), (Without<MouseFollow>, With<Gold>)>) {
-    // can't double loop the same query
-    let mut combinations = q_gold.iter_combinations_mut();
-    while let Some([a, b]) = combinations.fetch_next() {
-        // mutably access components data
+// weights are in units per second
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BoidParams {
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    pub neighbour_radius: f32,
+    // keeping loose gold on the board, enemies steer round towers themselves
+    pub avoidance: f32,
+    // how quickly it can change velocity, per second
+    pub max_force: f32,
+    pub max_speed: f32,
+    // fraction of velocity kept after a second
+    pub damping: f32,
+}
 
-        // ignore z
-        let (pos_a, mut boid_a) = (a.0.translation.truncate(), a.1);
-        let (pos_b, mut boid_b) = (b.0.translation.truncate(), b.1);
+impl BoidParams {
+    // loose gold slowly clumps together
+    fn gold() -> Self {
+        BoidParams {
+            separation: 60.0,
+            alignment: 0.0,
+            cohesion: 10.0,
+            neighbour_radius: 30.0,
+            avoidance: 80.0,
+            max_force: 240.0,
+            max_speed: 60.0,
+            damping: 0.2,
+        }
+    }
 
-        let d = pos_a.distance(pos_b);
-        if d < 10.0 {
-            let mut dir = pos_a - pos_b; // dir away
-                                         // could've just been d<0.1
-            if dir.x.abs() < 0.1 && dir.y.abs() < 0.1 {
-                // close enough to 0
-                let mut rng = rand::thread_rng();
-                dir = Vec2::new(rng.gen(), rng.gen());
-            }
-            dir = dir.normalize_or_zero() * (d + 0.01); // for when d = 0
+    // packs move the same way and stay together once they're moving
+    // steering decides when a squad has formed and can set off
+    fn enemy() -> Self {
+        BoidParams {
+            separation: 60.0,
+            alignment: 20.0,
+            cohesion: 15.0,
+            neighbour_radius: 40.0,
+            avoidance: 80.0,
+            max_force: 240.0,
+            max_speed: 60.0,
+            damping: 0.2,
+        }
+    }
+
+    const FIELDS: usize = 8;
+
+    fn field_mut(&mut self, i: usize) -> (&'static str, &mut f32) {
+        match i {
+            0 => ("separation", &mut self.separation),
+            1 => ("alignment", &mut self.alignment),
+            2 => ("cohesion", &mut self.cohesion),
+            3 => ("neighbour_radius", &mut self.neighbour_radius),
+            4 => ("avoidance", &mut self.avoidance),
+            5 => ("max_force", &mut self.max_force),
+            6 => ("max_speed", &mut self.max_speed),
+            _ => ("damping", &mut self.damping),
+        }
+    }
+}
+
+// F5 switches gold/enemies, F6 picks a value, F7/F8 lower/raise it
+// debug builds only
+pub struct BoidTuning {
+    pub gold: BoidParams,
+    pub enemy: BoidParams,
+    editing_enemy: bool,
+    field: usize,
+}
+
+impl Default for BoidTuning {
+    fn default() -> Self {
+        BoidTuning {
+            gold: BoidParams::gold(),
+            enemy: BoidParams::enemy(),
+            editing_enemy: false,
+            field: 0,
+        }
+    }
+}
+
+fn tune_boids(input: Res<Input<KeyCode>>, mut tuning: ResMut<BoidTuning>) {
+    if input.just_pressed(KeyCode::F5) {
+        tuning.editing_enemy = !tuning.editing_enemy;
+    }
+    if input.just_pressed(KeyCode::F6) {
+        tuning.field = (tuning.field + 1) % BoidParams::FIELDS;
+    }
+    let step = if input.just_pressed(KeyCode::F7) {
+        0.9
+    } else if input.just_pressed(KeyCode::F8) {
+        1.1
+    } else {
+        1.0
+    };
+    if !input.any_just_pressed([KeyCode::F5, KeyCode::F6, KeyCode::F7, KeyCode::F8]) {
+        return;
+    }
+
+    let field = tuning.field;
+    let editing_enemy = tuning.editing_enemy;
+    let params = if editing_enemy {
+        &mut tuning.enemy
+    } else {
+        &mut tuning.gold
+    };
+    let (_, value) = params.field_mut(field);
+    // lets a zero weight be turned back on
+    *value = if *value < 0.5 && step > 1.0 {
+        1.0
+    } else {
+        *value * step
+    };
+}
+
+#[derive(Component)]
+struct TuningLabel;
 
-            boid_a.sep_dir += dir;
-            boid_b.sep_dir -= dir;
+fn spawn_tuning_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 16.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(TuningLabel);
+}
+
+fn show_tuning(tuning: Res<BoidTuning>, mut q_label: Query<&mut Text, With<TuningLabel>>) {
+    if !tuning.is_changed() {
+        return;
+    }
+    let mut params = if tuning.editing_enemy {
+        tuning.enemy
+    } else {
+        tuning.gold
+    };
+    let (name, value) = params.field_mut(tuning.field);
+    for mut text in q_label.iter_mut() {
+        text.sections[0].value = format!(
+            "boids {}: {} = {:.1}",
+            if tuning.editing_enemy {
+                "enemy"
+            } else {
+                "gold"
+            },
+            name,
+            *value
+        );
+    }
+}
+
+// new boids take the current tuning, and everything updates when it changes
+fn give_params(
+    mut commands: Commands,
+    q_new: Query<(Entity, Option<&Enemy>), Added<Boid>>,
+    mut q_params: Query<(&mut BoidParams, Option<&Enemy>)>,
+    tuning: Res<BoidTuning>,
+) {
+    for (ent, enemy) in q_new.iter() {
+        let params = if enemy.is_some() {
+            tuning.enemy
+        } else {
+            tuning.gold
+        };
+        commands.entity(ent).insert(params);
+    }
+
+    if tuning.is_changed() {
+        for (mut params, enemy) in q_params.iter_mut() {
+            *params = if enemy.is_some() {
+                tuning.enemy
+            } else {
+                tuning.gold
+            };
         }
     }
 }
 
-fn boids_enemy(mut q_enemy: Query<(&Transform, &mut Boid), With<Enemy>>) {
-    let mut combinations = q_enemy.iter_combinations_mut();
-    while let Some([a, b]) = combinations.fetch_next() {
-        // mutably access components data
+fn boids_gold(
+    mut q_gold: Query<
+        (Entity, &Transform, &mut Boid, &BoidParams),
+        (
+            Without<MouseFollow>,
+            Without<OnConveyor>,
+            Without<Courier>,
+            With<Gold>,
+        ),
+    >,
+    index: Res<SpatialIndex>,
+) {
+    let forces: Vec<(Entity, Vec2)> = q_gold
+        .iter()
+        .map(|(ent, trans, _, params)| {
+            // ignore z
+            let pos = trans.translation.truncate();
+            let force = flock(ent, pos, params, &index.loose_gold, |other| {
+                q_gold
+                    .get(other)
+                    .ok()
+                    .map(|(_, trans, boid, _)| (trans.translation.truncate(), boid.heading))
+            });
+            (ent, force)
+        })
+        .collect();
+    for (ent, force) in forces {
+        if let Ok((_, _, mut boid, _)) = q_gold.get_mut(ent) {
+            boid.force += force;
+        }
+    }
+}
 
-        // ignore z
-        let (pos_a, mut boid_a) = (a.0.translation.truncate(), a.1);
-        let (pos_b, mut boid_b) = (b.0.translation.truncate(), b.1);
+// cohesion here only keeps packs together on the move
+// gathering a squad before it sets off is steering's regroup
+fn boids_enemy(
+    mut q_enemy: Query<(Entity, &Transform, &mut Boid, &BoidParams), With<Enemy>>,
+    index: Res<SpatialIndex>,
+) {
+    let forces: Vec<(Entity, Vec2)> = q_enemy
+        .iter()
+        .map(|(ent, trans, _, params)| {
+            let pos = trans.translation.truncate();
+            let force = flock(ent, pos, params, &index.enemies, |other| {
+                q_enemy
+                    .get(other)
+                    .ok()
+                    .map(|(_, trans, boid, _)| (trans.translation.truncate(), boid.heading))
+            });
+            (ent, force)
+        })
+        .collect();
+    for (ent, force) in forces {
+        if let Ok((_, _, mut boid, _)) = q_enemy.get_mut(ent) {
+            boid.force += force;
+        }
+    }
+}
 
-        let d = pos_a.distance(pos_b);
-        if d < 10.0 {
-            let mut dir = pos_a - pos_b; // dir away
+// loose gold never wanders off the board
+// anything dropped outside gets pulled back on
+fn gold_bounds(
+    mut q_gold: Query<
+        (&Transform, &mut Boid, &BoidParams),
+        (
+            Without<MouseFollow>,
+            Without<OnConveyor>,
+            Without<Courier>,
+            With<Gold>,
+        ),
+    >,
+) {
+    for (trans, mut boid, params) in q_gold.iter_mut() {
+        let past = past_board_edge(trans.translation.truncate(), BOARD_INSET);
+        boid.force -= past / BOARD_INSET * params.avoidance;
+    }
+}
+
+// separation, alignment and cohesion against everything nearby in the grid
+// the grid only finds candidates, positions come from this substep's transforms
+fn flock(
+    ent: Entity,
+    pos: Vec2,
+    params: &BoidParams,
+    grid: &SpatialGrid,
+    // (position, heading)
+    neighbour: impl Fn(Entity) -> Option<(Vec2, Vec2)>,
+) -> Vec2 {
+    let mut sep_dir = Vec2::ZERO;
+    let mut heading = Vec2::ZERO;
+    let mut center = Vec2::ZERO;
+    let mut count = 0;
+
+    let radius = params.neighbour_radius.max(SEPARATION_DIST) + INDEX_SLACK;
+    for (other, _) in grid.query(pos, radius) {
+        if other == ent {
+            continue;
+        }
+        let (other_pos, other_heading) = match neighbour(other) {
+            Some(found) => found,
+            None => continue,
+        };
+
+        let d = pos.distance(other_pos);
+        if d < SEPARATION_DIST {
+            let mut dir = pos - other_pos; // dir away
 
             if d < 0.01 {
                 // close enough to 0
                 let mut rng = rand::thread_rng();
-                dir = Vec2::new(rng.gen(), rng.gen());
+                dir = Vec2::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5);
             }
-            dir = dir.normalize_or_zero() * (d + 0.01); // for when d = 0
+            // barely touching barely pushes
+            sep_dir += dir.normalize_or_zero() * (1.0 - d / SEPARATION_DIST);
+        }
+        if d < params.neighbour_radius {
+            count += 1;
+            center += other_pos;
+            heading += other_heading;
+        }
+    }
 
-            boid_a.sep_dir += dir;
-            boid_b.sep_dir -= dir;
+    let mut force = sep_dir.clamp_length_max(1.0) * params.separation;
+    if count > 0 {
+        let count = count as f32;
+        force += (heading / count).normalize_or_zero() * params.alignment;
+        // gentler the closer it already is
+        force += (center / count - pos) / params.neighbour_radius * params.cohesion;
+    }
+    force
+}
+
+// actual movement, enemies add their own on top
+fn measure_headings(mut q_boid: Query<(&Transform, &mut Boid)>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+    for (trans, mut boid) in q_boid.iter_mut() {
+        let pos = trans.translation.truncate();
+        if let Some(last_pos) = boid.last_pos {
+            let moved = (pos - last_pos) / dt;
+            boid.heading = boid.heading.lerp(moved, HEADING_SMOOTHING);
         }
+        boid.last_pos = Some(pos);
     }
 }
 
-fn move_boids(mut q_boid: Query<(&mut Transform, &mut Boid)>, time: Res<Time>) {
-    for (mut trans, mut boid) in q_boid.iter_mut() {
-        trans.translation +=
-            boid.sep_dir.normalize_or_zero().extend(0.0) * time.delta_seconds() * 60.0;
-        boid.sep_dir = Vec2::ZERO;
+// carried gold is moved by whatever has it
+// and shouldn't drift off when it's let go
+fn hold_carried(
+    mut q_gold: Query<&mut Boid, Or<(With<MouseFollow>, With<OnConveyor>, With<Courier>)>>,
+) {
+    for mut boid in q_gold.iter_mut() {
+        boid.velocity = Vec2::ZERO;
+        boid.force = Vec2::ZERO;
+    }
+}
+
+// steer the velocity towards what the flock wants instead of jumping to it
+fn move_boids(
+    mut q_boid: Query<
+        (&mut Transform, &mut Boid, &BoidParams),
+        (Without<MouseFollow>, Without<OnConveyor>, Without<Courier>),
+    >,
+) {
+    for (mut trans, mut boid, params) in q_boid.iter_mut() {
+        let desired = boid.force.clamp_length_max(params.max_speed);
+        let accel = (desired - boid.velocity).clamp_length_max(params.max_force);
+        boid.velocity += accel * BOID_STEP;
+        boid.velocity *= params.damping.clamp(0.0, 1.0).powf(BOID_STEP);
+        boid.velocity = boid.velocity.clamp_length_max(params.max_speed);
+
+        trans.translation += (boid.velocity * BOID_STEP).extend(0.0);
+        boid.force = Vec2::ZERO;
     }
 }
@@ -0,0 +1,613 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::{
+    difficulty::Difficulty,
+    enemies::{Dead, EnemyKind, SpawnEnemyEvent},
+    gold::{Gold, GoldPile},
+    health::{Health, Resistances},
+    hexes::{Hex, HexCoords},
+    palette::*,
+    spatial::SpatialIndex,
+    stats::RunStats,
+    tower::Tower,
+    tutorial::AcceptInput,
+    waves::WaveStartedEvent,
+    MouseWorldPos, RestartEvent,
+};
+
+// wanders between points on this circle
+const BOSS_WANDER_RADIUS: f32 = 220.0;
+const BOSS_STEAL_RANGE: f32 = 50.0;
+const BOSS_STEAL_TIME: f32 = 0.5;
+const BOSS_ADDS_TIME: f32 = 8.0;
+// the warning square shows for this long before it lands
+const SLAM_WARN_TIME: f32 = 1.5;
+const SLAM_RADIUS: f32 = 60.0;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        let list = BossList(parse_bosses(include_str!("../assets/data/bosses.txt")));
+        app.add_event::<BossSpawnEvent>()
+            .add_event::<BossCapEvent>()
+            .insert_resource(BossDirector::new(&list))
+            .insert_resource(list)
+            .add_system(trigger_bosses)
+            .add_system(spawn_boss)
+            .add_system(boss_phase)
+            .add_system(move_boss.after(boss_phase))
+            .add_system(boss_steal)
+            .add_system(boss_adds.after(boss_phase))
+            .add_system(boss_slam.after(boss_phase))
+            .add_system(run_slams)
+            .add_system(check_defeat)
+            .add_system(reset_boss);
+    }
+}
+
+#[derive(Component)]
+pub struct Boss {
+    // index into the BossList
+    def: usize,
+    pub size: f32,
+}
+
+pub struct BossSpawnEvent {
+    pub def: usize,
+}
+
+// a boss has been beaten
+pub struct BossCapEvent {
+    // beating this one wins the game
+    pub final_boss: bool,
+}
+
+enum BossTrigger {
+    Towers(u32),
+    Wave(u32),
+    Banked(u32),
+    // seconds of play
+    Time(f32),
+}
+
+struct BossDef {
+    name: String,
+    trigger: BossTrigger,
+    // gold it takes to feed it
+    gold: u32,
+    // damage it takes to kill it
+    // feeding and damage both count towards beating it
+    hit_points: f32,
+    armor: f32,
+    size: f32,
+    // multiplies the speed of every phase
+    speed: f32,
+    // one per phase, falls back to the phase defaults
+    adds: Vec<(EnemyKind, u32)>,
+    slams: bool,
+    steals: bool,
+    // paid into the center pile when it's beaten
+    reward: u32,
+    final_boss: bool,
+}
+
+impl Default for BossDef {
+    fn default() -> Self {
+        BossDef {
+            name: "boss".to_string(),
+            trigger: BossTrigger::Towers(10),
+            gold: 750,
+            hit_points: 80.0,
+            armor: 0.25,
+            size: 80.0,
+            speed: 1.0,
+            adds: Vec::new(),
+            slams: true,
+            steals: true,
+            reward: 0,
+            final_boss: false,
+        }
+    }
+}
+
+struct BossList(Vec<BossDef>);
+
+// keeps track of which bosses have shown up
+struct BossDirector {
+    spawned: Vec<bool>,
+    // one at a time
+    active: bool,
+    wave: u32,
+}
+
+impl BossDirector {
+    fn new(list: &BossList) -> Self {
+        BossDirector {
+            spawned: vec![false; list.0.len()],
+            active: false,
+            wave: 0,
+        }
+    }
+}
+
+#[derive(Component)]
+struct BossBrain {
+    // 1 to 3, goes up as it gets closer to being beaten
+    phase: usize,
+    target: Vec2,
+    steal_timer: Timer,
+    adds_timer: Timer,
+    slam_timer: Timer,
+}
+
+// what changes between phases
+struct PhaseStats {
+    speed: f32,
+    adds: (EnemyKind, u32),
+    slam_time: f32,
+    // goes for the richest pile instead of wandering
+    hunts_piles: bool,
+    color: Color,
+}
+
+fn phase_stats(phase: usize) -> PhaseStats {
+    match phase {
+        1 => PhaseStats {
+            speed: 40.0,
+            adds: (EnemyKind::Grunt, 2),
+            slam_time: 9.0,
+            hunts_piles: false,
+            color: Color::WHITE,
+        },
+        2 => PhaseStats {
+            speed: 55.0,
+            adds: (EnemyKind::Thief, 3),
+            slam_time: 6.0,
+            hunts_piles: true,
+            color: ORANGE,
+        },
+        _ => PhaseStats {
+            speed: 70.0,
+            adds: (EnemyKind::Tank, 2),
+            slam_time: 4.0,
+            hunts_piles: true,
+            color: RED_PINK,
+        },
+    }
+}
+
+// a telegraphed hit at the cursor
+// any gold under it when it lands is gone
+#[derive(Component)]
+struct Slam {
+    timer: Timer,
+}
+
+// 0 when it shows up, 1 when it's beaten
+fn progress(pile: &GoldPile, health: &Health) -> f32 {
+    let fed = pile.count as f32 / pile.gold_cap as f32;
+    let hurt = 1.0 - (health.current / health.max).max(0.0);
+    fed + hurt
+}
+
+fn wander_point() -> Vec2 {
+    let angle = rand::thread_rng().gen_range(0.0..TAU);
+    Vec2::new(angle.cos(), angle.sin()) * BOSS_WANDER_RADIUS
+}
+
+fn parse_bosses(text: &str) -> Vec<BossDef> {
+    let mut bosses = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_no = i + 1;
+
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("boss") {
+            warn!("bosses.txt line {}: expected a boss", line_no);
+            continue;
+        }
+
+        let mut boss = BossDef::default();
+        for token in tokens {
+            if token == "final" {
+                boss.final_boss = true;
+                continue;
+            }
+            let (key, value) = match token.split_once('=') {
+                Some(kv) => kv,
+                None => {
+                    warn!("bosses.txt line {}: can't read {}", line_no, token);
+                    continue;
+                }
+            };
+            match key {
+                "name" => boss.name = value.to_string(),
+                "trigger" => match parse_trigger(value) {
+                    Some(trigger) => boss.trigger = trigger,
+                    None => warn!("bosses.txt line {}: unknown trigger {}", line_no, value),
+                },
+                "gold" => boss.gold = parse_number(value, boss.gold as f32, line_no) as u32,
+                "hp" => boss.hit_points = parse_number(value, boss.hit_points, line_no),
+                "armor" => boss.armor = parse_number(value, boss.armor, line_no),
+                "size" => boss.size = parse_number(value, boss.size, line_no),
+                "speed" => boss.speed = parse_number(value, boss.speed, line_no),
+                "reward" => boss.reward = parse_number(value, boss.reward as f32, line_no) as u32,
+                "slam" => boss.slams = value != "no",
+                "steal" => boss.steals = value != "no",
+                "adds" => {
+                    boss.adds = value
+                        .split(',')
+                        .filter_map(|group| parse_adds(group, line_no))
+                        .collect();
+                }
+                _ => warn!("bosses.txt line {}: unknown setting {}", line_no, key),
+            }
+        }
+        // can't be beaten with nothing to feed it
+        boss.gold = boss.gold.max(1);
+        boss.hit_points = boss.hit_points.max(1.0);
+        bosses.push(boss);
+    }
+
+    // the last one always ends the run
+    if let Some(last) = bosses.last_mut() {
+        last.final_boss = true;
+    }
+    bosses
+}
+
+fn parse_trigger(value: &str) -> Option<BossTrigger> {
+    let (kind, amount) = value.split_once(':')?;
+    let amount: f32 = amount.parse().ok()?;
+    match kind {
+        "towers" => Some(BossTrigger::Towers(amount as u32)),
+        "wave" => Some(BossTrigger::Wave(amount as u32)),
+        "banked" => Some(BossTrigger::Banked(amount as u32)),
+        "time" => Some(BossTrigger::Time(amount)),
+        _ => None,
+    }
+}
+
+fn parse_number(value: &str, default: f32, line_no: usize) -> f32 {
+    value.parse().unwrap_or_else(|_| {
+        warn!("bosses.txt line {}: {} isn't a number", line_no, value);
+        default
+    })
+}
+
+fn parse_adds(group: &str, line_no: usize) -> Option<(EnemyKind, u32)> {
+    let (kind, count) = group.split_once('*').unwrap_or((group, "1"));
+    match (EnemyKind::from_name(kind), count.parse()) {
+        (Some(kind), Ok(count)) => Some((kind, count)),
+        _ => {
+            warn!("bosses.txt line {}: can't read adds {}", line_no, group);
+            None
+        }
+    }
+}
+
+fn trigger_bosses(
+    mut director: ResMut<BossDirector>,
+    list: Res<BossList>,
+    mut ev_wave: EventReader<WaveStartedEvent>,
+    stats: Res<RunStats>,
+    accept: Res<AcceptInput>,
+    q_towers: Query<(), With<Tower>>,
+    mut ev_spawn: EventWriter<BossSpawnEvent>,
+) {
+    for ev in ev_wave.iter() {
+        director.wave = director.wave.max(ev.wave);
+    }
+    if !accept.0 || stats.over || director.active {
+        return;
+    }
+
+    let towers = q_towers.iter().count() as u32;
+    // final bosses wait until everything else has had its turn
+    let others_done = list
+        .0
+        .iter()
+        .zip(director.spawned.iter())
+        .all(|(def, spawned)| def.final_boss || *spawned);
+    for (i, def) in list.0.iter().enumerate() {
+        if director.spawned[i] || (def.final_boss && !others_done) {
+            continue;
+        }
+        let ready = match def.trigger {
+            BossTrigger::Towers(count) => towers >= count,
+            BossTrigger::Wave(wave) => director.wave >= wave,
+            BossTrigger::Banked(gold) => stats.gold_banked >= gold,
+            BossTrigger::Time(secs) => stats.time >= secs,
+        };
+        if ready {
+            director.spawned[i] = true;
+            director.active = true;
+            ev_spawn.send(BossSpawnEvent { def: i });
+            break;
+        }
+    }
+}
+
+fn spawn_boss(
+    mut commands: Commands,
+    mut ev_boss_spawn: EventReader<BossSpawnEvent>,
+    list: Res<BossList>,
+    asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
+) {
+    for ev in ev_boss_spawn.iter() {
+        let def = match list.0.get(ev.def) {
+            Some(def) => def,
+            None => continue,
+        };
+        let stats = phase_stats(1);
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: asset_server.load("sprites/Monster.png"),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(def.size, def.size)),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: Vec3::new(400.0, 20.0, 0.2),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Boss {
+                def: ev.def,
+                size: def.size,
+            })
+            .insert(GoldPile {
+                count: 0,
+                gold_cap: ((def.gold as f32 * difficulty.boss_health) as u32).max(1),
+            })
+            .insert(Health::new(
+                def.hit_points * difficulty.boss_health,
+                def.armor,
+                Resistances::default(),
+            ))
+            .insert(BossBrain {
+                phase: 1,
+                target: wander_point(),
+                steal_timer: Timer::from_seconds(BOSS_STEAL_TIME, true),
+                adds_timer: Timer::from_seconds(BOSS_ADDS_TIME, true),
+                slam_timer: Timer::from_seconds(stats.slam_time, false),
+            });
+    }
+}
+
+fn boss_phase(
+    mut q_boss: Query<(&Transform, &GoldPile, &Health, &mut BossBrain, &mut Sprite), With<Boss>>,
+) {
+    for (trans, pile, health, mut brain, mut sprite) in q_boss.iter_mut() {
+        let p = progress(pile, health);
+        let phase = if p < 0.33 {
+            1
+        } else if p < 0.66 {
+            2
+        } else {
+            3
+        };
+        // only ever gets angrier
+        if phase > brain.phase {
+            brain.phase = phase;
+            let stats = phase_stats(phase);
+            sprite.color = stats.color;
+            // pick a new target straight away
+            brain.target = trans.translation.truncate();
+        }
+    }
+}
+
+fn move_boss(
+    mut q_boss: Query<(&mut Transform, &Boss, &mut BossBrain), Without<Dead>>,
+    q_piles: Query<(&Transform, &GoldPile), (With<Hex>, Without<Boss>)>,
+    list: Res<BossList>,
+    time: Res<Time>,
+) {
+    for (mut trans, boss, mut brain) in q_boss.iter_mut() {
+        let stats = phase_stats(brain.phase);
+        let speed = stats.speed * list.0[boss.def].speed;
+        let pos = trans.translation.truncate();
+        let dir = brain.target - pos;
+        let step = speed * time.delta_seconds();
+        if dir.length() > step {
+            trans.translation += dir.normalize_or_zero().extend(0.0) * step;
+            continue;
+        }
+
+        // arrived, pick somewhere else to go
+        let richest = q_piles
+            .iter()
+            .filter(|(_, pile)| pile.count > 0)
+            .max_by_key(|(_, pile)| pile.count)
+            .map(|(pile_trans, _)| pile_trans.translation.truncate());
+        brain.target = match richest {
+            // don't sit on one pile forever
+            Some(pile) if stats.hunts_piles && pile.distance(pos) > BOSS_STEAL_RANGE => pile,
+            _ => wander_point(),
+        };
+    }
+}
+
+// takes from any pile it walks over
+// anything it takes counts as stolen
+fn boss_steal(
+    mut q_boss: Query<(&Transform, &Boss, &mut BossBrain), Without<Dead>>,
+    mut q_piles: Query<(&Hex, &mut GoldPile), Without<Boss>>,
+    index: Res<SpatialIndex>,
+    mut stats: ResMut<RunStats>,
+    list: Res<BossList>,
+    time: Res<Time>,
+) {
+    for (trans, boss, mut brain) in q_boss.iter_mut() {
+        if !list.0[boss.def].steals {
+            continue;
+        }
+        if !brain.steal_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let pos = trans.translation.truncate();
+        for (pile_ent, pile_pos) in index.piles.query(pos, BOSS_STEAL_RANGE) {
+            if pile_pos.distance(pos) > BOSS_STEAL_RANGE {
+                continue;
+            }
+            if let Ok((hex, mut pile)) = q_piles.get_mut(pile_ent) {
+                if pile.count == 0 {
+                    continue;
+                }
+                pile.count -= 1;
+                stats.gold_stolen += 1;
+                // check_loss decides if that ends the run
+                if pile.count == 0 && hex.coords.is_same(HexCoords::new()) {
+                    stats.center_emptied = true;
+                }
+            }
+        }
+    }
+}
+
+fn boss_adds(
+    mut q_boss: Query<(&Transform, &Boss, &mut BossBrain), Without<Dead>>,
+    mut ev_spawn_enemy: EventWriter<SpawnEnemyEvent>,
+    list: Res<BossList>,
+    time: Res<Time>,
+) {
+    for (trans, boss, mut brain) in q_boss.iter_mut() {
+        if !brain.adds_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let def = &list.0[boss.def];
+        let (kind, count) = def
+            .adds
+            .get(brain.phase - 1)
+            .copied()
+            .unwrap_or(phase_stats(brain.phase).adds);
+        for i in 0..count {
+            let angle = i as f32 * TAU / count as f32;
+            let offset = Vec2::new(angle.cos(), angle.sin()) * boss.size * 0.6;
+            ev_spawn_enemy.send(SpawnEnemyEvent {
+                kind,
+                position: (trans.translation.truncate() + offset).extend(0.3),
+                speed: 1.0,
+                wave: 0,
+            });
+        }
+    }
+}
+
+fn boss_slam(
+    mut commands: Commands,
+    mut q_boss: Query<(&Boss, &mut BossBrain), Without<Dead>>,
+    mouse: Res<MouseWorldPos>,
+    list: Res<BossList>,
+    time: Res<Time>,
+) {
+    for (boss, mut brain) in q_boss.iter_mut() {
+        if !list.0[boss.def].slams {
+            continue;
+        }
+        if !brain.slam_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let slam_time = phase_stats(brain.phase).slam_time;
+        brain.slam_timer = Timer::from_seconds(slam_time, false);
+
+        let mut color = RED_PINK;
+        color.set_a(0.1);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::new(SLAM_RADIUS * 2.0, SLAM_RADIUS * 2.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(mouse.0.extend(0.7)),
+                ..default()
+            })
+            .insert(Slam {
+                timer: Timer::from_seconds(SLAM_WARN_TIME, false),
+            });
+    }
+}
+
+fn run_slams(
+    mut commands: Commands,
+    mut q_slams: Query<(Entity, &Transform, &mut Slam, &mut Sprite)>,
+    q_gold: Query<&Transform, With<Gold>>,
+    index: Res<SpatialIndex>,
+    time: Res<Time>,
+) {
+    for (ent, trans, mut slam, mut sprite) in q_slams.iter_mut() {
+        slam.timer.tick(time.delta());
+        // gets more solid the closer it is to landing
+        sprite.color.set_a(0.1 + 0.5 * slam.timer.percent());
+        if !slam.timer.finished() {
+            continue;
+        }
+
+        let pos = trans.translation.truncate();
+        // carried gold too, that's the point
+        for (gold_ent, _) in index.gold.query(pos, SLAM_RADIUS) {
+            if let Ok(gold_trans) = q_gold.get(gold_ent) {
+                let offset = gold_trans.translation.truncate() - pos;
+                // it's a square
+                if offset.x.abs() < SLAM_RADIUS && offset.y.abs() < SLAM_RADIUS {
+                    commands.entity(gold_ent).despawn_recursive();
+                }
+            }
+        }
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+fn check_defeat(
+    mut commands: Commands,
+    q_boss: Query<(Entity, &Boss, &GoldPile, &Health, Option<&Dead>)>,
+    mut q_piles: Query<(&Hex, &mut GoldPile), Without<Boss>>,
+    q_slams: Query<Entity, With<Slam>>,
+    list: Res<BossList>,
+    mut director: ResMut<BossDirector>,
+    mut ev_boss_cap: EventWriter<BossCapEvent>,
+) {
+    for (ent, boss, pile, health, dead) in q_boss.iter() {
+        if dead.is_some() || progress(pile, health) >= 1.0 {
+            let def = &list.0[boss.def];
+            for (hex, mut pile) in q_piles.iter_mut() {
+                if hex.coords.is_same(HexCoords::new()) {
+                    pile.count = (pile.count + def.reward).min(pile.gold_cap);
+                }
+            }
+            director.active = false;
+            ev_boss_cap.send(BossCapEvent {
+                final_boss: def.final_boss,
+            });
+            commands.entity(ent).despawn_recursive();
+            for slam in q_slams.iter() {
+                commands.entity(slam).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn reset_boss(
+    mut commands: Commands,
+    mut ev_restart: EventReader<RestartEvent>,
+    q_boss: Query<Entity, Or<(With<Boss>, With<Slam>)>>,
+    list: Res<BossList>,
+    mut director: ResMut<BossDirector>,
+) {
+    for _ev in ev_restart.iter() {
+        *director = BossDirector::new(&list);
+        for ent in q_boss.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
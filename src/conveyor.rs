@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+
+use crate::{
+    gold::{take_from_center_pile, Gold, GoldPile, MouseFollow, PileRemoveEvent},
+    hexes::{Hex, HexCoords, Selection},
+    indicators::NoticeEvent,
+    palette::*,
+    spatial::SpatialIndex,
+    tower::Tower,
+    tutorial::AcceptInput,
+    RestartEvent,
+};
+
+// paid out of the center pile
+const CONVEYOR_COST_PER_HEX: u32 = 2;
+const CONVEYOR_UPKEEP: u32 = 1;
+const CONVEYOR_UPKEEP_TIME: f32 = 10.0;
+// about the collector's neighbours
+const COLLECTOR_RANGE: f32 = 45.0;
+const COLLECTOR_PULL_TIME: f32 = 0.5;
+const CONVEYOR_SPEED: f32 = 80.0;
+
+pub struct ConveyorPlugin;
+
+impl Plugin for ConveyorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ConveyorDraft { path: Vec::new() })
+            .add_system(draft_input)
+            .add_system(extend_draft.after(draft_input))
+            .add_system(pay_upkeep)
+            .add_system(collect_gold)
+            .add_system(move_conveyor_gold)
+            .add_system(remove_conveyor)
+            .add_system(reset_conveyors);
+    }
+}
+
+// a collector hex at the start of the path
+// and a line of hexes that ends at a pile
+#[derive(Component)]
+pub struct Conveyor {
+    start: HexCoords,
+    // the pile it feeds
+    end: HexCoords,
+    path: Vec<Vec2>,
+    pull_timer: Timer,
+    upkeep_timer: Timer,
+    // stops when upkeep can't be paid
+    powered: bool,
+}
+
+impl Conveyor {
+    fn new(start: HexCoords, end: HexCoords, path: Vec<Vec2>) -> Self {
+        Conveyor {
+            start,
+            end,
+            path,
+            pull_timer: Timer::from_seconds(COLLECTOR_PULL_TIME, true),
+            upkeep_timer: Timer::from_seconds(CONVEYOR_UPKEEP_TIME, true),
+            powered: true,
+        }
+    }
+}
+
+// gold riding a conveyor
+// still Gold so enemies can grab it off the belt
+#[derive(Component)]
+pub struct OnConveyor {
+    conveyor: Entity,
+    // index into the conveyor's path
+    next: usize,
+}
+
+#[derive(Component)]
+struct DraftMarker(usize);
+
+// the path the player is currently drawing
+struct ConveyorDraft {
+    path: Vec<(HexCoords, Vec2)>,
+}
+
+fn spawn_draft_marker(commands: &mut Commands, pos: Vec2, index: usize) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: MEDIUM_BLUE,
+                custom_size: Some(Vec2::new(8.0, 8.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(pos.extend(0.25)),
+            ..default()
+        })
+        .insert(DraftMarker(index));
+}
+
+fn is_neighbour(a: HexCoords, b: HexCoords) -> bool {
+    a.get_neighbours().iter().any(|n| n.is_same(b))
+}
+
+// C on an empty hex starts a collector
+// then C on a pile finishes the path
+// Esc cancels
+fn draft_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    accept: Res<AcceptInput>,
+    mut draft: ResMut<ConveyorDraft>,
+    q_selection: Query<(Entity, &Transform, &Hex, Option<&Tower>), With<Selection>>,
+    mut q_piles: Query<(&Hex, &mut GoldPile)>,
+    q_markers: Query<Entity, With<DraftMarker>>,
+    mut ev_notice: EventWriter<NoticeEvent>,
+) {
+    if !accept.0 {
+        return;
+    }
+
+    if input.just_pressed(KeyCode::Escape) {
+        draft.path.clear();
+        for ent in q_markers.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+        return;
+    }
+
+    if !input.just_pressed(KeyCode::C) {
+        return;
+    }
+
+    for (ent, trans, hex, tower) in q_selection.iter() {
+        let is_pile = q_piles.get(ent).is_ok();
+        if draft.path.is_empty() {
+            // collectors go on empty hexes
+            if is_pile || tower.is_some() {
+                continue;
+            }
+            let pos = trans.translation.truncate();
+            draft.path.push((hex.coords, pos));
+            spawn_draft_marker(&mut commands, pos, 0);
+        } else if is_pile {
+            let (last, _) = draft.path[draft.path.len() - 1];
+            if !last.is_same(hex.coords) {
+                if !is_neighbour(last, hex.coords) {
+                    ev_notice.send(NoticeEvent(
+                        "Conveyor has to end next to the path".to_string(),
+                    ));
+                    continue;
+                }
+                draft.path.push((hex.coords, trans.translation.truncate()));
+            }
+
+            let cost = CONVEYOR_COST_PER_HEX * draft.path.len() as u32;
+            if !take_from_center_pile(&mut q_piles, cost) {
+                ev_notice.send(NoticeEvent(format!(
+                    "Not enough gold for a conveyor, needs {}",
+                    cost
+                )));
+                continue;
+            }
+
+            let start = draft.path[0].0;
+            let end = hex.coords;
+            let path: Vec<Vec2> = draft.path.iter().map(|(_, pos)| *pos).collect();
+            commands
+                .spawn_bundle(SpatialBundle::default())
+                .with_children(|parent| {
+                    for (i, pos) in path.iter().enumerate() {
+                        // bigger square for the collector
+                        let (color, size) = if i == 0 {
+                            (LIGHT_BLUE, 14.0)
+                        } else {
+                            (ORANGE, 6.0)
+                        };
+                        parent.spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                color,
+                                custom_size: Some(Vec2::new(size, size)),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(pos.extend(0.25)),
+                            ..default()
+                        });
+                    }
+                })
+                .insert(Conveyor::new(start, end, path));
+
+            draft.path.clear();
+            for ent in q_markers.iter() {
+                commands.entity(ent).despawn_recursive();
+            }
+        }
+    }
+}
+
+// hovering a neighbour of the end of the path adds it
+// hovering back over the previous hex undoes the last one
+fn extend_draft(
+    mut commands: Commands,
+    mut draft: ResMut<ConveyorDraft>,
+    q_selection: Query<(&Transform, &Hex), With<Selection>>,
+    q_markers: Query<(Entity, &DraftMarker)>,
+) {
+    if draft.path.is_empty() {
+        return;
+    }
+
+    for (trans, hex) in q_selection.iter() {
+        let len = draft.path.len();
+        let (last, _) = draft.path[len - 1];
+        if last.is_same(hex.coords) {
+            continue;
+        }
+
+        if len > 1 && draft.path[len - 2].0.is_same(hex.coords) {
+            draft.path.pop();
+            for (ent, marker) in q_markers.iter() {
+                if marker.0 == len - 1 {
+                    commands.entity(ent).despawn_recursive();
+                }
+            }
+        } else if is_neighbour(last, hex.coords)
+            && !draft.path.iter().any(|(c, _)| c.is_same(hex.coords))
+        {
+            let pos = trans.translation.truncate();
+            draft.path.push((hex.coords, pos));
+            spawn_draft_marker(&mut commands, pos, len);
+        }
+    }
+}
+
+fn pay_upkeep(
+    mut q_conveyors: Query<&mut Conveyor>,
+    mut q_piles: Query<(&Hex, &mut GoldPile)>,
+    time: Res<Time>,
+) {
+    for mut conveyor in q_conveyors.iter_mut() {
+        if conveyor.upkeep_timer.tick(time.delta()).just_finished() {
+            conveyor.powered = take_from_center_pile(&mut q_piles, CONVEYOR_UPKEEP);
+        }
+    }
+}
+
+fn collect_gold(
+    mut commands: Commands,
+    mut q_conveyors: Query<(Entity, &mut Conveyor)>,
+    q_gold: Query<Entity, (With<Gold>, Without<MouseFollow>, Without<OnConveyor>)>,
+    index: Res<SpatialIndex>,
+    time: Res<Time>,
+) {
+    for (ent, mut conveyor) in q_conveyors.iter_mut() {
+        if !conveyor.powered {
+            continue;
+        }
+        if conveyor.pull_timer.tick(time.delta()).just_finished() {
+            // one piece at a time
+            let collector = conveyor.path[0];
+            for (gold_ent, gold_pos) in index.loose_gold.query(collector, COLLECTOR_RANGE) {
+                if gold_pos.distance(collector) < COLLECTOR_RANGE && q_gold.contains(gold_ent) {
+                    commands.entity(gold_ent).insert(OnConveyor {
+                        conveyor: ent,
+                        next: 0,
+                    });
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// anything that can't go any further is dropped as loose gold
+fn move_conveyor_gold(
+    mut commands: Commands,
+    mut q_gold: Query<(Entity, &mut Transform, &mut OnConveyor), With<Gold>>,
+    q_conveyors: Query<&Conveyor>,
+    time: Res<Time>,
+) {
+    for (gold_ent, mut trans, mut on) in q_gold.iter_mut() {
+        let conveyor = match q_conveyors.get(on.conveyor) {
+            Ok(conveyor) if conveyor.powered => conveyor,
+            // stopped or gone
+            _ => {
+                commands.entity(gold_ent).remove::<OnConveyor>();
+                continue;
+            }
+        };
+        match conveyor.path.get(on.next) {
+            Some(&target) => {
+                let dir = target - trans.translation.truncate();
+                let step = CONVEYOR_SPEED * time.delta_seconds();
+                if dir.length() <= step {
+                    trans.translation.x = target.x;
+                    trans.translation.y = target.y;
+                    on.next += 1;
+                } else {
+                    trans.translation += dir.normalize_or_zero().extend(0.0) * step;
+                }
+            }
+            None => {
+                // at the end, store_gold takes it if the pile has room
+                // otherwise it stays there as loose gold
+                commands.entity(gold_ent).remove::<OnConveyor>();
+            }
+        }
+    }
+}
+
+// X on either end tears the conveyor down
+// so does the end pile going away some other way
+// whatever was riding it is dropped where it is
+fn remove_conveyor(
+    mut commands: Commands,
+    mut ev_remove: EventReader<PileRemoveEvent>,
+    q_conveyors: Query<(Entity, &Conveyor)>,
+    q_piles: Query<&Hex, With<GoldPile>>,
+) {
+    let removed: Vec<HexCoords> = ev_remove.iter().map(|ev| ev.coords).collect();
+    for (ent, conveyor) in q_conveyors.iter() {
+        let torn_down = removed
+            .iter()
+            .any(|coords| coords.is_same(conveyor.start) || coords.is_same(conveyor.end));
+        let end_gone = !q_piles.iter().any(|hex| hex.coords.is_same(conveyor.end));
+        if torn_down || end_gone {
+            // move_conveyor_gold drops its gold
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
+
+fn reset_conveyors(
+    mut commands: Commands,
+    mut ev_restart: EventReader<RestartEvent>,
+    mut draft: ResMut<ConveyorDraft>,
+    q_conveyors: Query<Entity, Or<(With<Conveyor>, With<DraftMarker>)>>,
+) {
+    for _ev in ev_restart.iter() {
+        draft.path.clear();
+        for ent in q_conveyors.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
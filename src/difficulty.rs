@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+
+use crate::{
+    gold::{DecayOutcome, GoldDecaySettings},
+    stats::{LossRules, RunStats},
+    waves::{WaveClearedEvent, WaveStartedEvent},
+    RestartEvent,
+};
+
+// how far the adaptive mode can push wave sizes
+const ADAPT_STEP_UP: f32 = 0.1;
+const ADAPT_STEP_DOWN: f32 = 0.15;
+const ADAPT_MIN: f32 = 0.5;
+const ADAPT_MAX: f32 = 2.0;
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Difficulty::new(Preset::Normal))
+            .insert_resource(EconomyLedger::default())
+            .add_system(difficulty_buttons)
+            .add_system(apply_difficulty)
+            .add_system(track_economy)
+            .add_system(reset_ledger);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Preset {
+    Easy,
+    Normal,
+    Hard,
+    // read from assets/data/difficulty.txt
+    Custom,
+}
+
+pub struct Difficulty {
+    pub preset: Preset,
+    // multiplies the delay before and between spawns
+    pub spawn_delay: f32,
+    // multiplies how many enemies are in each wave
+    pub group_size: f32,
+    // multiplies boss gold and hit points
+    pub boss_health: f32,
+    pub tower_cost_growth: u32,
+    pub gold_spawn_time: f32,
+    // loose gold disappears instead of going back to a hex
+    pub gold_vanishes: bool,
+    // grows and shrinks the waves based on how the last one went
+    pub adaptive: bool,
+    pub wave_scale: f32,
+    // what loses the run, copied into LossRules
+    pub max_stolen: Option<u32>,
+    pub center_pile_empty: bool,
+    pub lives: Option<u32>,
+}
+
+impl Difficulty {
+    pub fn new(preset: Preset) -> Self {
+        let normal = Difficulty {
+            preset,
+            spawn_delay: 1.0,
+            group_size: 1.0,
+            boss_health: 1.0,
+            tower_cost_growth: 2,
+            gold_spawn_time: 10.0,
+            gold_vanishes: false,
+            adaptive: false,
+            wave_scale: 1.0,
+            max_stolen: Some(100),
+            center_pile_empty: false,
+            lives: None,
+        };
+        match preset {
+            Preset::Easy => Difficulty {
+                spawn_delay: 1.3,
+                group_size: 0.7,
+                boss_health: 0.75,
+                tower_cost_growth: 1,
+                gold_spawn_time: 8.0,
+                max_stolen: Some(150),
+                ..normal
+            },
+            Preset::Normal => normal,
+            Preset::Hard => Difficulty {
+                spawn_delay: 0.8,
+                group_size: 1.4,
+                boss_health: 1.5,
+                tower_cost_growth: 3,
+                gold_spawn_time: 12.0,
+                gold_vanishes: true,
+                max_stolen: Some(75),
+                lives: Some(25),
+                ..normal
+            },
+            Preset::Custom => parse_custom(include_str!("../assets/data/difficulty.txt"), normal),
+        }
+    }
+
+    // everything that scales the number of enemies
+    pub fn wave_size(&self) -> f32 {
+        self.group_size * self.wave_scale
+    }
+}
+
+fn parse_custom(text: &str, mut custom: Difficulty) -> Difficulty {
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_no = i + 1;
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                warn!("difficulty.txt line {}: can't read {}", line_no, line);
+                continue;
+            }
+        };
+        if key == "gold_vanishes" {
+            custom.gold_vanishes = value == "yes";
+            continue;
+        }
+        if key == "center_pile_empty" {
+            custom.center_pile_empty = value == "yes";
+            continue;
+        }
+        let number: f32 = match value.parse() {
+            Ok(number) => number,
+            Err(_) => {
+                warn!("difficulty.txt line {}: {} isn't a number", line_no, value);
+                continue;
+            }
+        };
+        match key {
+            "spawn_delay" => custom.spawn_delay = number,
+            "group_size" => custom.group_size = number,
+            "boss_health" => custom.boss_health = number,
+            "tower_cost_growth" => custom.tower_cost_growth = number as u32,
+            "gold_spawn_time" => custom.gold_spawn_time = number.max(0.1),
+            // 0 turns the rule off
+            "max_stolen" => custom.max_stolen = Some(number as u32).filter(|n| *n > 0),
+            "lives" => custom.lives = Some(number as u32).filter(|n| *n > 0),
+            _ => warn!("difficulty.txt line {}: unknown setting {}", line_no, key),
+        }
+    }
+    custom
+}
+
+// on the start menu
+#[derive(Component)]
+pub enum DifficultyButton {
+    Preset(Preset),
+    Adaptive,
+}
+
+// gold in and out over each wave
+#[derive(Default)]
+pub struct EconomyLedger {
+    waves: Vec<WaveLedger>,
+    // (wave, stolen, banked) when it started
+    open: Vec<(u32, u32, u32)>,
+}
+
+struct WaveLedger {
+    wave: u32,
+    stolen: u32,
+    banked: u32,
+}
+
+fn difficulty_buttons(
+    q_interaction: Query<(&Interaction, &DifficultyButton), Changed<Interaction>>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    for (interaction, button) in q_interaction.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            DifficultyButton::Preset(preset) => {
+                let adaptive = difficulty.adaptive;
+                *difficulty = Difficulty::new(*preset);
+                difficulty.adaptive = adaptive;
+            }
+            DifficultyButton::Adaptive => difficulty.adaptive = !difficulty.adaptive,
+        }
+        info!(
+            "Difficulty: {:?}, adaptive: {:?}",
+            difficulty.preset, difficulty.adaptive
+        );
+    }
+}
+
+// the rest is read where it's used
+fn apply_difficulty(
+    difficulty: Res<Difficulty>,
+    mut decay: ResMut<GoldDecaySettings>,
+    mut rules: ResMut<LossRules>,
+) {
+    if difficulty.is_changed() {
+        decay.outcome = if difficulty.gold_vanishes {
+            DecayOutcome::Vanish
+        } else {
+            DecayOutcome::ReturnToHex
+        };
+        *rules = LossRules {
+            max_stolen: difficulty.max_stolen,
+            center_pile_empty: difficulty.center_pile_empty,
+            lives: difficulty.lives,
+        };
+    }
+}
+
+fn track_economy(
+    mut ev_started: EventReader<WaveStartedEvent>,
+    mut ev_cleared: EventReader<WaveClearedEvent>,
+    mut ledger: ResMut<EconomyLedger>,
+    mut difficulty: ResMut<Difficulty>,
+    stats: Res<RunStats>,
+) {
+    for ev in ev_started.iter() {
+        ledger
+            .open
+            .push((ev.wave, stats.gold_stolen, stats.gold_banked));
+    }
+
+    for ev in ev_cleared.iter() {
+        let (stolen, banked) = match ledger.open.iter().position(|open| open.0 == ev.wave) {
+            Some(i) => {
+                let (_, stolen, banked) = ledger.open.remove(i);
+                (
+                    stats.gold_stolen.saturating_sub(stolen),
+                    stats.gold_banked.saturating_sub(banked),
+                )
+            }
+            None => continue,
+        };
+        ledger.waves.push(WaveLedger {
+            wave: ev.wave,
+            stolen,
+            banked,
+        });
+
+        if difficulty.adaptive {
+            adapt(&mut difficulty, &ledger);
+        }
+    }
+}
+
+// doing well, send more. losing a lot, send fewer
+fn adapt(difficulty: &mut Difficulty, ledger: &EconomyLedger) {
+    let last = match ledger.waves.last() {
+        Some(last) => last,
+        None => return,
+    };
+    if last.stolen == 0 && last.banked > 0 {
+        difficulty.wave_scale = (difficulty.wave_scale + ADAPT_STEP_UP).min(ADAPT_MAX);
+    } else if last.stolen * 2 > last.banked {
+        difficulty.wave_scale = (difficulty.wave_scale - ADAPT_STEP_DOWN).max(ADAPT_MIN);
+    }
+    debug!(
+        "Wave {:?}: stolen {:?}, banked {:?}, next waves x{:.2}",
+        last.wave, last.stolen, last.banked, difficulty.wave_scale
+    );
+}
+
+fn reset_ledger(
+    mut ev_restart: EventReader<RestartEvent>,
+    mut ledger: ResMut<EconomyLedger>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    for _ev in ev_restart.iter() {
+        *ledger = EconomyLedger::default();
+        difficulty.wave_scale = 1.0;
+    }
+}
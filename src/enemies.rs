@@ -1,176 +1,460 @@
 use bevy::sprite::collide_aabb::collide;
-use bevy::utils::Duration;
 use bevy::{prelude::*, utils::FloatOrd};
-use rand::prelude::*;
 
 use crate::boids::Boid;
-use crate::gold::GoldPile;
-use crate::tower::bullet_hit;
-use crate::StartSpawningEnemiesEvent;
+use crate::conveyor::OnConveyor;
+use crate::gold::{spawn_gold_bundle, GoldAge, GoldDecaySettings, GoldPile};
+use crate::health::{apply_damage, Health, Resistances};
+use crate::hexes::{Hex, HexCoords};
+use crate::spatial::SpatialIndex;
+use crate::stats::RunStats;
+use crate::steering::{Steering, SteeringWeights, TOWER_CLEARANCE};
+use crate::transfer::Courier;
+use crate::waves::WaveTag;
+use crate::RestartEvent;
 use crate::{gold::Gold, palette::*};
 
-const ENEMY_SPAWN_TIME: f32 = 10.0;
-const BOSS_HEALTH: u32 = 750; //1000
+// raiders take one piece from a pile this often
+const RAID_TIME: f32 = 0.6;
+// how close to the middle of the pile they need to be
+const RAID_RANGE: f32 = 20.0;
+// how long frost slows for
+const CHILL_TIME: f32 = 2.0;
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<SpawnEnemyEvent>()
-            .add_event::<BossSpawnEvent>()
-            .add_event::<BossCapEvent>()
-            .insert_resource(EnemySpawnInfo { group_size: 10 })
-            .add_system(setup)
-            .add_system(generate_enemies)
             .add_system(spawn_enemy)
             .add_system(move_enemies)
-            .add_system(grab_gold.before(bullet_hit))
+            .add_system(grab_gold.before(apply_damage))
+            .add_system(raid_piles.before(move_enemies))
             .add_system(escape)
-            .add_system(spawn_boss)
-            // bullet_hit adds Dead. Run before it so it runs next frame
+            .add_system(thaw)
+            .add_system(reset_enemies)
+            // apply_damage adds Dead. Run before it so it runs next frame
             // and then this entity won't be added to any other queries
             // what's the pattern?
             // run die code before the thing that sets Dead?
-            .add_system(drop_gold_and_die.before(bullet_hit));
+            .add_system(drop_gold_and_die.before(apply_damage));
     }
 }
 
 #[derive(Component)]
 pub struct Enemy {
-    has_gold: bool,
+    pub kind: EnemyKind,
+    // gold held
+    carrying: u32,
+    capacity: u32,
+    speed: f32,
+    size: f32,
+    raid_timer: Timer,
+    // stands still while taking from a pile
+    raiding: bool,
 }
 
 impl Enemy {
-    fn new() -> Self {
-        Enemy { has_gold: false }
+    fn new(kind: EnemyKind, speed_mult: f32) -> Self {
+        let stats = kind.stats();
+        Enemy {
+            kind,
+            carrying: 0,
+            capacity: stats.capacity,
+            speed: stats.speed * speed_mult,
+            size: stats.size,
+            raid_timer: Timer::from_seconds(RAID_TIME, true),
+            raiding: false,
+        }
     }
-}
 
-#[derive(Component)]
-pub struct Dead;
-
-#[derive(Component)]
-struct EnemySpawner {
-    timer: Timer,
-}
+    pub fn has_gold(&self) -> bool {
+        self.carrying > 0
+    }
 
-#[derive(Component)]
-pub struct Boss;
+    pub fn carrying(&self) -> u32 {
+        self.carrying
+    }
 
-pub struct BossSpawnEvent;
-pub struct BossCapEvent;
+    // heads for the exit once full
+    fn is_full(&self) -> bool {
+        self.carrying >= self.capacity
+    }
 
-struct SpawnEnemyEvent {
-    position: Vec3,
+    pub fn size(&self) -> f32 {
+        self.size
+    }
 }
 
-struct EnemySpawnInfo {
-    group_size: u32,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyKind {
+    Grunt,
+    // fast, grabs one and runs
+    Thief,
+    // slow, takes a few hits and can carry a lot
+    Tank,
+    // breaks into splitlings when it dies
+    Splitter,
+    Splitling,
+    // flies over towers and doesn't bump into anything
+    Flyer,
 }
 
-fn setup(mut commands: Commands, mut ev_start: EventReader<StartSpawningEnemiesEvent>) {
-    for ev in ev_start.iter() {
-        commands.spawn().insert(EnemySpawner {
-            timer: Timer::new(Duration::from_secs_f32(ENEMY_SPAWN_TIME), true),
-        });
-    }
+pub struct EnemyStats {
+    pub speed: f32,
+    // speed multiplier when running away with gold
+    pub flee_speed: f32,
+    pub health: f32,
+    pub armor: f32,
+    pub resist: Resistances,
+    pub size: f32,
+    pub capacity: u32,
+    pub color: Color,
+    pub texture: Option<&'static str>,
+    // how many splitlings on death
+    pub splits: u32,
+    pub flying: bool,
+    // goes after piles as well as loose gold
+    pub raids: bool,
+    pub steering: SteeringWeights,
 }
 
-fn generate_enemies(
-    time: Res<Time>,
-    mut ev_spawn_enemy: EventWriter<SpawnEnemyEvent>,
-    mut q_spawner: Query<&mut EnemySpawner>,
-    mut info: ResMut<EnemySpawnInfo>,
-) {
-    for mut spawner in q_spawner.iter_mut() {
-        if spawner.timer.tick(time.delta()).finished() {
-            for _ in 0..info.group_size {
-                let mut rng = rand::thread_rng();
-                let spawn_pos = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0))
-                    .normalize_or_zero()
-                    * 500.;
-
-                ev_spawn_enemy.send(SpawnEnemyEvent {
-                    position: spawn_pos.extend(0.3),
-                })
-            }
-            info.group_size += 1;
+impl EnemyKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "grunt" => Some(EnemyKind::Grunt),
+            "thief" => Some(EnemyKind::Thief),
+            "tank" => Some(EnemyKind::Tank),
+            "splitter" => Some(EnemyKind::Splitter),
+            "splitling" => Some(EnemyKind::Splitling),
+            "flyer" => Some(EnemyKind::Flyer),
+            _ => None,
         }
     }
-}
 
-fn spawn_enemy(mut commands: Commands, mut ev_spawn_enemy: EventReader<SpawnEnemyEvent>) {
-    for ev in ev_spawn_enemy.iter() {
-        commands
-            .spawn_bundle(SpriteBundle {
-                sprite: Sprite {
-                    color: CRIMSON,
-                    custom_size: Some(Vec2::new(15.0, 15.0)),
-                    ..default()
+    pub fn stats(self) -> EnemyStats {
+        let grunt = EnemyStats {
+            speed: 100.0,
+            flee_speed: 1.0,
+            health: 1.0,
+            armor: 0.0,
+            resist: Resistances::default(),
+            size: 15.0,
+            capacity: 1,
+            color: CRIMSON,
+            texture: None,
+            splits: 0,
+            flying: false,
+            raids: false,
+            steering: SteeringWeights {
+                seek: 1.0,
+                avoid_towers: 0.6,
+                tower_clearance: TOWER_CLEARANCE,
+                scatter: 1.0,
+                regroup: 0.8,
+                escort: 0.6,
+            },
+        };
+        match self {
+            EnemyKind::Grunt => grunt,
+            EnemyKind::Thief => EnemyStats {
+                speed: 160.0,
+                flee_speed: 1.25,
+                size: 11.0,
+                color: PINK,
+                raids: true,
+                // sneaks around towers and works alone
+                steering: SteeringWeights {
+                    seek: 1.0,
+                    avoid_towers: 1.2,
+                    tower_clearance: TOWER_CLEARANCE,
+                    scatter: 1.5,
+                    regroup: 0.0,
+                    escort: 0.0,
                 },
-                transform: Transform {
-                    translation: ev.position,
-                    ..default()
+                ..grunt
+            },
+            EnemyKind::Tank => EnemyStats {
+                speed: 60.0,
+                health: 6.0,
+                armor: 0.5,
+                // bullets bounce off
+                resist: Resistances {
+                    kinetic: 0.3,
+                    explosive: 0.0,
+                    frost: 0.5,
                 },
-                ..default()
-            })
-            .insert(Enemy::new())
-            .insert(Boid::new());
+                size: 24.0,
+                capacity: 3,
+                // a big plain block, the monster sprite is for bosses
+                color: DARK_ORANGE,
+                raids: true,
+                // walks straight through tower fire and guards carriers
+                steering: SteeringWeights {
+                    seek: 1.0,
+                    avoid_towers: 0.0,
+                    tower_clearance: TOWER_CLEARANCE,
+                    scatter: 0.0,
+                    regroup: 0.5,
+                    escort: 1.0,
+                },
+                ..grunt
+            },
+            EnemyKind::Splitter => EnemyStats {
+                speed: 90.0,
+                health: 2.0,
+                size: 18.0,
+                color: MAGENTA,
+                splits: 3,
+                steering: SteeringWeights {
+                    seek: 1.0,
+                    avoid_towers: 0.4,
+                    tower_clearance: TOWER_CLEARANCE,
+                    scatter: 0.5,
+                    regroup: 1.0,
+                    escort: 0.4,
+                },
+                ..grunt
+            },
+            EnemyKind::Splitling => EnemyStats {
+                speed: 130.0,
+                size: 9.0,
+                color: RED_PINK,
+                // already in a bunch when they split
+                steering: SteeringWeights {
+                    seek: 1.0,
+                    avoid_towers: 0.3,
+                    tower_clearance: TOWER_CLEARANCE,
+                    scatter: 1.5,
+                    regroup: 0.0,
+                    escort: 0.3,
+                },
+                ..grunt
+            },
+            EnemyKind::Flyer => EnemyStats {
+                speed: 110.0,
+                size: 13.0,
+                color: PURPLE,
+                // shells burst under them
+                resist: Resistances {
+                    kinetic: 0.0,
+                    explosive: 0.5,
+                    frost: 0.0,
+                },
+                flying: true,
+                // towers aren't in the way up there
+                steering: SteeringWeights {
+                    seek: 1.0,
+                    avoid_towers: 0.0,
+                    tower_clearance: 0.0,
+                    scatter: 0.8,
+                    regroup: 0.5,
+                    escort: 0.0,
+                },
+                ..grunt
+            },
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Flying;
+
+#[derive(Component)]
+pub struct Dead;
+
+// slowed down by frost
+#[derive(Component)]
+pub struct Chilled {
+    // speed multiplier
+    slow: f32,
+    timer: Timer,
+}
+
+impl Chilled {
+    pub fn new(slow: f32) -> Self {
+        Chilled {
+            slow,
+            timer: Timer::from_seconds(CHILL_TIME, false),
+        }
     }
 }
 
-fn spawn_boss(
+pub struct SpawnEnemyEvent {
+    pub kind: EnemyKind,
+    pub position: Vec3,
+    // multiplies the base speed
+    pub speed: f32,
+    pub wave: u32,
+}
+
+fn spawn_enemy(
     mut commands: Commands,
-    mut ev_boss_spawn: EventReader<BossSpawnEvent>,
+    mut ev_spawn_enemy: EventReader<SpawnEnemyEvent>,
     asset_server: Res<AssetServer>,
 ) {
-    for _ev in ev_boss_spawn.iter() {
-        commands
-            .spawn_bundle(SpriteBundle {
-                texture: asset_server.load("sprites/Monster.png"),
-                transform: Transform {
-                    translation: Vec3::new(400.0, 20.0, 0.2),
-                    ..default()
-                },
+    for ev in ev_spawn_enemy.iter() {
+        let stats = ev.kind.stats();
+        let mut position = ev.position;
+        if stats.flying {
+            // above the towers
+            position.z = 0.6;
+        }
+
+        let mut bundle = SpriteBundle {
+            sprite: Sprite {
+                color: stats.color,
+                custom_size: Some(Vec2::new(stats.size, stats.size)),
                 ..default()
-            })
-            .insert(Boss)
-            .insert(GoldPile {
-                count: 0,
-                gold_cap: BOSS_HEALTH,
-            });
+            },
+            transform: Transform {
+                translation: position,
+                ..default()
+            },
+            ..default()
+        };
+        if let Some(texture) = stats.texture {
+            bundle.texture = asset_server.load(texture);
+        }
+
+        let mut enemy = commands.spawn_bundle(bundle);
+        enemy
+            .insert(Enemy::new(ev.kind, ev.speed))
+            .insert(Health::new(stats.health, stats.armor, stats.resist))
+            .insert(Steering::new(stats.steering))
+            .insert(WaveTag(ev.wave));
+        if stats.flying {
+            enemy.insert(Flying);
+        } else {
+            enemy.insert(Boid::new());
+        }
     }
 }
 
 pub fn move_enemies(
-    mut q_enemies: Query<(&mut Transform, &Enemy), Without<Dead>>,
+    mut q_enemies: Query<(&mut Transform, &Enemy, &Steering, Option<&Chilled>), Without<Dead>>,
     q_gold: Query<&Transform, (With<Gold>, Without<Enemy>)>,
+    q_piles: Query<(&Transform, &GoldPile), (With<Hex>, Without<Enemy>, Without<Gold>)>,
     time: Res<Time>,
 ) {
-    for (mut trans, enemy) in q_enemies.iter_mut() {
+    for (mut trans, enemy, steering, chilled) in q_enemies.iter_mut() {
+        if enemy.raiding {
+            continue;
+        }
+
         let mut dir = Vec3::new(0.0, 0.0, 0.0) - trans.translation;
 
-        if enemy.has_gold {
+        let mut speed = enemy.speed;
+        if let Some(chilled) = chilled {
+            speed *= chilled.slow;
+        }
+        if enemy.is_full() {
             dir = trans.translation - Vec3::ZERO;
+            speed *= enemy.kind.stats().flee_speed;
         } else {
-            let direction = q_gold
+            let mut closest = q_gold
                 .iter()
-                .min_by_key(|target_transform| {
-                    FloatOrd(Vec3::distance(
-                        target_transform.translation,
-                        trans.translation,
-                    ))
-                })
-                .map(|closest_target| closest_target.translation - trans.translation);
-
-            if let Some(direction) = direction {
-                dir = direction;
+                .map(|target_transform| target_transform.translation)
+                .min_by_key(|target| FloatOrd(Vec3::distance(*target, trans.translation)));
+
+            if enemy.kind.stats().raids {
+                // a pile with anything in it is fair game too
+                let pile = q_piles
+                    .iter()
+                    .filter(|(_, pile)| pile.count > 0)
+                    .map(|(pile_trans, _)| pile_trans.translation)
+                    .min_by_key(|target| FloatOrd(Vec3::distance(*target, trans.translation)));
+                closest = match (closest, pile) {
+                    (Some(gold), Some(pile)) => {
+                        if pile.distance(trans.translation) < gold.distance(trans.translation) {
+                            Some(pile)
+                        } else {
+                            Some(gold)
+                        }
+                    }
+                    (gold, pile) => gold.or(pile),
+                };
+            }
+
+            if let Some(closest) = closest {
+                dir = closest - trans.translation;
             }
         }
 
-        dir.z = 0.0;
+        // everything else pulls it off the straight line
+        let dir = dir.truncate().normalize_or_zero() * steering.seek + steering.force;
 
-        trans.translation += dir.normalize_or_zero() * 100. * time.delta_seconds();
+        trans.translation += dir.clamp_length_max(1.0).extend(0.0) * speed * time.delta_seconds();
+    }
+}
+
+fn thaw(mut commands: Commands, mut q_chilled: Query<(Entity, &mut Chilled)>, time: Res<Time>) {
+    for (ent, mut chilled) in q_chilled.iter_mut() {
+        if chilled.timer.tick(time.delta()).just_finished() {
+            commands.entity(ent).remove::<Chilled>();
+        }
+    }
+}
+
+// raiders stand on a pile and take from it a piece at a time
+// then leave with a stack like any other carrier
+fn raid_piles(
+    mut commands: Commands,
+    mut q_enemies: Query<(Entity, &Transform, &mut Enemy), Without<Dead>>,
+    mut q_piles: Query<(&Hex, &mut GoldPile)>,
+    index: Res<SpatialIndex>,
+    asset_server: Res<AssetServer>,
+    decay: Res<GoldDecaySettings>,
+    time: Res<Time>,
+    mut stats: ResMut<RunStats>,
+) {
+    for (ent, trans, mut enemy) in q_enemies.iter_mut() {
+        enemy.raiding = false;
+        if !enemy.kind.stats().raids || enemy.is_full() {
+            continue;
+        }
+
+        let pos = trans.translation.truncate();
+        let target = index
+            .piles
+            .query(pos, RAID_RANGE)
+            .find(|&(pile_ent, pile_pos)| {
+                pile_pos.distance(pos) < RAID_RANGE
+                    && matches!(q_piles.get(pile_ent), Ok((_, pile)) if pile.count > 0)
+            })
+            .map(|(pile_ent, _)| pile_ent);
+
+        let pile_ent = match target {
+            Some(pile_ent) => pile_ent,
+            None => {
+                enemy.raid_timer.reset();
+                continue;
+            }
+        };
+
+        enemy.raiding = true;
+        if !enemy.raid_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        if let Ok((hex, mut pile)) = q_piles.get_mut(pile_ent) {
+            pile.count -= 1;
+            enemy.carrying += 1;
+
+            // carried gold keeps its age so it can be dropped later
+            let gold = spawn_gold_bundle(&mut commands, &asset_server, Vec3::ZERO, decay.lifetime);
+            commands.entity(gold).remove::<Gold>().insert(Transform {
+                translation: Vec3::new(0.0, 4.0 * (enemy.carrying - 1) as f32, 0.1),
+                scale: Vec3::ONE * 2.0,
+                ..default()
+            });
+            commands.entity(ent).add_child(gold);
+
+            // check_loss decides if that ends the run
+            if pile.count == 0 && hex.coords.is_same(HexCoords::new()) {
+                stats.center_emptied = true;
+            }
+        }
     }
 }
 
@@ -180,29 +464,39 @@ pub fn move_enemies(
 // die and spawn a gold on the corpse
 fn grab_gold(
     mut commands: Commands,
-    mut q_enemies: Query<(Entity, &Transform, &mut Enemy), Without<Dead>>,
+    mut q_enemies: Query<(&Transform, &mut Enemy), Without<Dead>>,
     mut q_gold: Query<(Entity, &mut Transform), (Without<Enemy>, With<Gold>)>,
+    index: Res<SpatialIndex>,
 ) {
     // when you grab the gold, run away
     // directly away from 0,0 ?
     // remove the gold?
     // add something to the enemy so they don't pick up more gold?
     for (ent, mut gold_trans) in q_gold.iter_mut() {
-        for (e_ent, e_trans, mut enemy) in q_enemies.iter_mut() {
-            if enemy.has_gold {
+        let nearby = index.enemies.query(gold_trans.translation.truncate(), 25.0);
+        for (e_ent, _) in nearby {
+            let (e_trans, mut enemy) = match q_enemies.get_mut(e_ent) {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+            if enemy.is_full() {
                 // don't pick up more gold
                 // could've been an insert and Without<CarryingGold>
-                break;
+                continue;
             }
             if let Some(_) = collide(
                 gold_trans.translation,
                 Vec2::new(8., 12.),
                 e_trans.translation,
-                Vec2::new(15., 15.),
+                Vec2::new(enemy.size, enemy.size),
             ) {
                 //println!("Grabbed a gold: ent: {:?}", ent);
-                enemy.has_gold = true;
-                commands.entity(ent).remove::<Gold>();
+                enemy.carrying += 1;
+                commands
+                    .entity(ent)
+                    .remove::<Gold>()
+                    .remove::<OnConveyor>()
+                    .remove::<Courier>();
 
                 //println!("Add Child. e_ent: {:?}", e_ent);
                 // here
@@ -215,7 +509,8 @@ fn grab_gold(
                 // when hit, send an event to delete the enemy
                 // probably not
                 commands.entity(e_ent).add_child(ent);
-                gold_trans.translation = Vec3::new(0.0, 0.0, 0.1);
+                // stack them up
+                gold_trans.translation = Vec3::new(0.0, 4.0 * (enemy.carrying - 1) as f32, 0.1);
                 break;
             }
         }
@@ -224,58 +519,89 @@ fn grab_gold(
 
 fn drop_gold_and_die(
     mut commands: Commands,
-    q_enemies: Query<(Entity, &Enemy, &Transform, Option<&Children>), Added<Dead>>,
-    mut q_child: Query<(&mut Transform, &Sprite), Without<Enemy>>,
+    q_enemies: Query<
+        (
+            Entity,
+            &Enemy,
+            &Transform,
+            Option<&Children>,
+            Option<&WaveTag>,
+        ),
+        Added<Dead>,
+    >,
+    // carried gold still has its age
+    mut q_child: Query<&mut Transform, (With<GoldAge>, Without<Enemy>)>,
+    decay: Res<GoldDecaySettings>,
+    mut ev_spawn_enemy: EventWriter<SpawnEnemyEvent>,
 ) {
-    for (ent, enemy, e_trans, children) in q_enemies.iter() {
-        if enemy.has_gold {
+    for (ent, enemy, e_trans, children, wave) in q_enemies.iter() {
+        if enemy.has_gold() {
             if let Some(children) = children {
-                // print!("Has some children");
-                // println!(" len: {:?}", children.len());
+                let mut dropped = Vec::new();
                 for &child in children.iter() {
-                    //commands.entity(ent).remove_children(child);
-                    // println!("Adding gold. len: {:?}", children.len());
-
-                    // print!("Add Gold ");
-                    // println!("child ent: {:?}", child);
                     // it's probably immediately colliding with gold
-                    commands.entity(child).insert(Gold);
-                    let child_trans = q_child.get_mut(child);
-                    match child_trans {
-                        Ok(mut t) => {
-                            t.0.translation = e_trans.translation;
-                            t.0.translation.z = 0.3;
-                        }
-                        Err(e) => {
-                            error!("Error getting child transform: {e}");
-                        }
+                    if let Ok(mut t) = q_child.get_mut(child) {
+                        commands
+                            .entity(child)
+                            .insert(Gold)
+                            .insert(GoldAge::new(decay.lifetime));
+                        t.translation = e_trans.translation;
+                        t.translation.z = 0.3;
+                        dropped.push(child);
                     }
                 }
-                // print!("Remove children");
-                // println!(" ent: {:?}", ent);
-                commands.entity(ent).remove_children(&children);
+                commands.entity(ent).remove_children(&dropped);
                 // pos is (0, 0)
             }
         }
+
+        let stats = enemy.kind.stats();
+        for i in 0..stats.splits {
+            let angle = i as f32 * std::f32::consts::TAU / stats.splits as f32;
+            let offset = Vec2::new(angle.cos(), angle.sin()) * 10.0;
+            ev_spawn_enemy.send(SpawnEnemyEvent {
+                kind: EnemyKind::Splitling,
+                position: e_trans.translation + offset.extend(0.0),
+                speed: enemy.speed / stats.speed,
+                wave: wave.map(|w| w.0).unwrap_or(0),
+            });
+        }
+
         // kill enemy
-        // print!("Kill Enemy ");
-        // println!("ent: {:?}", ent);
         // Kill Enemy ent: 217v0
         // thread 'main' panicked at 'Entity 217v0 does not exist'
         // did a bullet hit the leftover gold?
         // maybe rapier_2d has logging for this type of thing
-        commands.entity(ent).despawn();
+        commands.entity(ent).despawn_recursive();
     }
 }
 
-fn escape(mut commands: Commands, q_enemies: Query<(Entity, &Enemy, &Transform), Without<Dead>>) {
+fn escape(
+    mut commands: Commands,
+    q_enemies: Query<(Entity, &Enemy, &Transform), Without<Dead>>,
+    mut stats: ResMut<RunStats>,
+) {
     for (ent, enemy, trans) in q_enemies.iter() {
-        if enemy.has_gold {
+        if enemy.has_gold() {
             if trans.translation.distance(Vec3::ZERO) > 700.0 {
                 // escaped
-                println!("Escaped");
+                println!("Escaped with {:?}", enemy.carrying);
+                stats.gold_stolen += enemy.carrying;
+                stats.escaped += 1;
                 commands.entity(ent).despawn_recursive();
             }
         }
     }
 }
+
+fn reset_enemies(
+    mut commands: Commands,
+    mut ev_restart: EventReader<RestartEvent>,
+    q_enemies: Query<Entity, With<Enemy>>,
+) {
+    for _ev in ev_restart.iter() {
+        for ent in q_enemies.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
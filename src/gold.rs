@@ -1,16 +1,37 @@
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 use bevy::sprite::collide_aabb::collide;
-use bevy::utils::Duration;
+use bevy::utils::{Duration, FloatOrd};
 
 use crate::boids::Boid;
-use crate::enemies::{BossCapEvent, Boss};
+use crate::conveyor::OnConveyor;
+use crate::boss::Boss;
 use crate::hexes::{Hex, HexCoords, Selection, DEG_TO_RAD};
+use crate::indicators::NoticeEvent;
 use crate::palette::*;
+use crate::spatial::SpatialIndex;
+use crate::stats::RunStats;
 use crate::tower::{Tower, TowerPreview};
-use crate::MouseWorldPos;
-
-const GOLD_SPAWN_TIME: f32 = 10.0;
+use crate::transfer::Courier;
+use crate::waves::WaveStartedEvent;
+use crate::{MouseWorldPos, RestartEvent};
+
+// how long loose gold lasts on the ground
+const GOLD_DECAY_TIME: f32 = 45.0;
+// the last few seconds it fades out
+const GOLD_FADE_TIME: f32 = 10.0;
+const BANK_COST: u32 = 25;
+// paid every wave
+const BANK_INTEREST: f32 = 0.05;
+// (gold_cap, cost to upgrade into it)
+const VAULT_TIERS: [(u32, u32); 3] = [(500, 0), (1000, 50), (2000, 150)];
+// stack sprite is full size at this many
+const STACK_FULL: f32 = 200.0;
+// what the center pile starts with
+const STARTING_GOLD: u32 = 6;
+// collision boxes for storing gold, bosses use their own size
+const GOLD_SIZE: Vec2 = Vec2::new(8., 12.);
+const PILE_SIZE: f32 = 20.0;
 
 pub struct GoldPlugin;
 
@@ -20,13 +41,24 @@ impl Plugin for GoldPlugin {
             .add_event::<PileCapEvent>()
             .add_event::<PileSpawnEvent>()
             .add_event::<PileRemoveEvent>()
+            .add_event::<PileUpgradeEvent>()
+            .add_event::<WithdrawGoldEvent>()
             .add_event::<SpawnGoldEvent>()
             .add_event::<DelayedGoldEvent>()
             .add_event::<DelayedGoldEventHelper>()
+            .insert_resource(GoldDecaySettings {
+                lifetime: GOLD_DECAY_TIME,
+                fade_time: GOLD_FADE_TIME,
+                outcome: DecayOutcome::ReturnToHex,
+            })
             .add_startup_system(setup)
             .add_system(pile_input)
             .add_system(spawn_pile)
             .add_system(remove_pile)
+            .add_system(upgrade_pile)
+            .add_system(pay_interest)
+            .add_system(withdraw_gold)
+            .add_system(clear_withdrawn)
             .add_system(generate_gold)
             .add_system(spawn_gold)
             .add_system(delay_gold)
@@ -37,8 +69,11 @@ impl Plugin for GoldPlugin {
             .add_system(move_gold)
             .add_system(check_mouse)
             .add_system(store_gold)
+            .add_system(age_gold)
             .add_system(make_health_bar)
-            .add_system(animate_health_bar);
+            .add_system(animate_health_bar)
+            .add_system(animate_stack)
+            .add_system(reset_gold);
     }
 }
 
@@ -49,12 +84,17 @@ pub struct GoldSpawner {
 }
 
 impl GoldSpawner {
-    pub fn new() -> Self {
+    pub fn new(spawn_time: f32) -> Self {
         GoldSpawner {
-            timer: Timer::new(Duration::from_secs_f32(GOLD_SPAWN_TIME), true),
+            timer: Timer::new(Duration::from_secs_f32(spawn_time), true),
             gold_gen: 1,
         }
     }
+
+    // keeps how far along the current wait is
+    pub fn set_spawn_time(&mut self, spawn_time: f32) {
+        self.timer.set_duration(Duration::from_secs_f32(spawn_time));
+    }
 }
 
 pub struct SpawnGoldEvent {
@@ -78,11 +118,43 @@ pub struct Gold;
 #[derive(Component)]
 pub struct MouseFollow;
 
+// loose gold on the ground slowly decays
+// carried gold doesn't age
+#[derive(Component)]
+pub struct GoldAge {
+    timer: Timer,
+}
+
+impl GoldAge {
+    pub fn new(lifetime: f32) -> Self {
+        GoldAge {
+            timer: Timer::from_seconds(lifetime, false),
+        }
+    }
+}
+
+pub enum DecayOutcome {
+    // goes back into the nearest hex as ore
+    ReturnToHex,
+    Vanish,
+}
+
+pub struct GoldDecaySettings {
+    pub lifetime: f32,
+    pub fade_time: f32,
+    pub outcome: DecayOutcome,
+}
+
 // need to move mouse close to pick up gold
 // but then need to move farther away to break the tether and drop it
 const TETHER_BREAK_DIST: f32 = 250.0;
 const TETHER_ENTER_DIST: f32 = 90.0;
 const GOLD_MOVE_SPEED: f32 = 225.0;
+// carried gold circles the mouse, further out the more there is
+const ORBIT_SPACING: f32 = 6.0;
+const ORBIT_SPIN: f32 = 1.5;
+// mouse this close to a pile pulls everything into it
+const ORBIT_COLLAPSE_DIST: f32 = 20.0;
 
 #[derive(Component)]
 pub struct GoldPile {
@@ -113,6 +185,47 @@ impl PileSpawnEvent {
     }
 }
 
+// player piles can be upgraded
+// previews and the boss can't
+#[derive(Component)]
+pub struct Vault {
+    tier: usize,
+}
+
+// pays interest on what's stored every wave
+#[derive(Component)]
+pub struct Bank;
+
+#[derive(Component)]
+struct PileStack;
+
+// taken out of a pile
+// can't go back into that same pile until it's been moved away
+#[derive(Component)]
+pub struct Withdrawn {
+    pub pile: Entity,
+}
+
+// came out of a player pile, so it was already counted as banked
+// stays on after Withdrawn comes off
+#[derive(Component)]
+pub struct Banked;
+
+pub enum PileUpgrade {
+    Bank,
+    Vault,
+}
+
+pub struct PileUpgradeEvent {
+    pub coords: HexCoords,
+    pub upgrade: PileUpgrade,
+}
+
+pub struct WithdrawGoldEvent {
+    pub coords: HexCoords,
+    pub amount: u32,
+}
+
 pub struct PileCapEvent {
     pub coords: HexCoords,
 }
@@ -123,36 +236,62 @@ pub struct PileRemoveEvent {
 
 fn store_gold(
     mut commands: Commands,
-    q_gold: Query<(Entity, &Transform, &Gold)>,
-    mut q_pile: Query<(&Transform, &mut GoldPile, Option<&Hex>)>,
+    q_gold: Query<
+        (Entity, &Transform, Option<&Withdrawn>, Option<&Courier>, Option<&Banked>),
+        With<Gold>,
+    >,
+    mut q_pile: Query<(&Transform, &mut GoldPile, Option<&Hex>, Option<&Boss>)>,
+    q_bosses: Query<&Boss>,
+    index: Res<SpatialIndex>,
     mut ev_cap: EventWriter<PileCapEvent>,
-    mut ev_boss_cap: EventWriter<BossCapEvent>,
+    mut stats: ResMut<RunStats>,
 ) {
-    for (gold_ent, gold_trans, _gold) in q_gold.iter() {
-        for (pile_trans, mut pile, hex) in q_pile.iter_mut() {
-            let mut b_size = Vec2::new(20., 20.);
-            if let None = hex {
-                // boss
-                b_size = Vec2::new(80., 80.);
+    // far enough to touch the biggest pile around
+    let biggest = q_bosses.iter().map(|boss| boss.size).fold(PILE_SIZE, f32::max);
+    let reach = biggest / 2.0 + GOLD_SIZE.max_element() / 2.0;
+    for (gold_ent, gold_trans, withdrawn, courier, banked) in q_gold.iter() {
+        let nearby = index.piles.query(gold_trans.translation.truncate(), reach);
+        for (pile_ent, _) in nearby {
+            if let Some(withdrawn) = withdrawn {
+                if withdrawn.pile == pile_ent {
+                    continue;
+                }
             }
+            // transfers only drop off at their destination
+            if let Some(courier) = courier {
+                if courier.dest != pile_ent {
+                    continue;
+                }
+            }
+            if let Ok((pile_trans, mut pile, hex, boss)) = q_pile.get_mut(pile_ent) {
+                let mut b_size = Vec2::splat(PILE_SIZE);
+                if let Some(boss) = boss {
+                    b_size = Vec2::new(boss.size, boss.size);
+                }
 
-            if let Some(_) = collide(
-                gold_trans.translation,
-                Vec2::new(8., 12.),
-                pile_trans.translation,
-                b_size,
-            ) {
-                if pile.count < pile.gold_cap {
-                    pile.count += 1;
-                    //println!("Plink! {:?}", pile.count);
-                    commands.entity(gold_ent).despawn_recursive();
-                    if pile.count == pile.gold_cap {
-                        //println!("Cap reached!");
-                        if let Some(hex) = hex {
-                            ev_cap.send(PileCapEvent { coords: hex.coords });
-                        } else {
-                            ev_boss_cap.send(BossCapEvent);
+                if let Some(_) = collide(
+                    gold_trans.translation,
+                    GOLD_SIZE,
+                    pile_trans.translation,
+                    b_size,
+                ) {
+                    if pile.count < pile.gold_cap {
+                        pile.count += 1;
+                        // moving gold between piles isn't new gold
+                        if hex.is_some() && courier.is_none() && banked.is_none() {
+                            stats.gold_banked += 1;
                         }
+                        //println!("Plink! {:?}", pile.count);
+                        commands.entity(gold_ent).despawn_recursive();
+                        if pile.count == pile.gold_cap {
+                            //println!("Cap reached!");
+                            // the boss keeps track of being fed itself
+                            if let Some(hex) = hex {
+                                ev_cap.send(PileCapEvent { coords: hex.coords });
+                            }
+                        }
+                        // only goes in one pile
+                        break;
                     }
                 }
             }
@@ -164,14 +303,66 @@ fn setup(mut ev_spawn: EventWriter<PileSpawnEvent>) {
     // spawn a pile at the center with some starting cash
     ev_spawn.send(PileSpawnEvent {
         coords: HexCoords::new(),
-        starting_gold: 6,
+        starting_gold: STARTING_GOLD,
     });
 }
 
+// towers clear their own hexes
+fn reset_gold(
+    mut commands: Commands,
+    mut ev_restart: EventReader<RestartEvent>,
+    mut ev_spawn: EventWriter<PileSpawnEvent>,
+    q_gold: Query<Entity, (With<GoldAge>, Without<Parent>)>,
+    mut q_piles: Query<
+        (Entity, &Hex, &mut GoldPile, Option<&mut Vault>, Option<&Children>),
+        (Without<Tower>, Without<TowerPreview>),
+    >,
+) {
+    for _ev in ev_restart.iter() {
+        for ent in q_gold.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+
+        let mut found_center = false;
+        for (ent, hex, mut pile, vault, children) in q_piles.iter_mut() {
+            if hex.coords.is_same(HexCoords::new()) {
+                // keep the center pile, just put it back how it was
+                found_center = true;
+                pile.count = STARTING_GOLD;
+                pile.gold_cap = VAULT_TIERS[0].0;
+                if let Some(mut vault) = vault {
+                    vault.tier = 0;
+                }
+                commands.entity(ent).remove::<Bank>();
+                continue;
+            }
+
+            if let Some(children) = children {
+                for &child in children {
+                    commands.entity(child).despawn_recursive();
+                }
+            }
+            commands
+                .entity(ent)
+                .remove::<GoldPile>()
+                .remove::<Vault>()
+                .remove::<Bank>();
+        }
+
+        if !found_center {
+            ev_spawn.send(PileSpawnEvent {
+                coords: HexCoords::new(),
+                starting_gold: STARTING_GOLD,
+            });
+        }
+    }
+}
+
 fn spawn_pile(
     mut commands: Commands,
     mut ev_spawn: EventReader<PileSpawnEvent>,
     q_hexes: Query<(Entity, &Hex), Without<TowerPreview>>,
+    asset_server: Res<AssetServer>,
 ) {
     // don't run before hexes exist
     // this preserves the event that is send frame ~1
@@ -186,8 +377,9 @@ fn spawn_pile(
                         .entity(ent)
                         .insert(GoldPile {
                             count: ev.starting_gold,
-                            gold_cap: 500,
+                            gold_cap: VAULT_TIERS[0].0,
                         })
+                        .insert(Vault { tier: 0 })
                         .with_children(|parent| {
                             parent.spawn_bundle(SpriteBundle {
                                 sprite: Sprite {
@@ -208,6 +400,21 @@ fn spawn_pile(
                                 },
                                 ..default()
                             });
+                            parent
+                                .spawn_bundle(SpriteBundle {
+                                    texture: asset_server.load("sprites/Gold3.png"),
+                                    transform: Transform {
+                                        translation: Vec3 {
+                                            x: 0.0,
+                                            y: 0.0,
+                                            z: 0.25,
+                                        },
+                                        rotation: Quat::from_rotation_z(-30.0 * DEG_TO_RAD),
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .insert(PileStack);
                         });
                 }
             }
@@ -219,7 +426,11 @@ fn remove_pile(
     mut commands: Commands,
     mut ev_remove: EventReader<PileRemoveEvent>,
     mut ev_spawn_gold: EventWriter<SpawnGoldEvent>,
-    q_piles: Query<(Entity, &Children, &Transform, &Hex, &GoldPile)>,
+    // towers and previews refund their own gold
+    q_piles: Query<
+        (Entity, &Children, &Transform, &Hex, &GoldPile),
+        (Without<Tower>, Without<TowerPreview>),
+    >,
     //mut q_child: Query<&mut Sprite>,
 ) {
     for ev in ev_remove.iter() {
@@ -240,7 +451,9 @@ fn remove_pile(
                     .entity(ent)
                     // didn't work
                     //.remove_children(children)
-                    .remove::<GoldPile>();
+                    .remove::<GoldPile>()
+                    .remove::<Vault>()
+                    .remove::<Bank>();
             }
         }
     }
@@ -249,14 +462,18 @@ fn remove_pile(
 #[derive(Component)]
 struct HealthBar;
 
+// the dark box behind the bar, goes with it
+#[derive(Component)]
+struct HealthBarBack;
+
 fn make_health_bar(mut commands: Commands, q_new: Query<(Entity, Option<&Boss>), Added<GoldPile>>) {
     for (ent, boss) in q_new.iter() {
         let mut r = Quat::from_rotation_z(-30.0 * DEG_TO_RAD);
         let mut y = 0.0;
         let mut x = -8.0;
-        if let Some(_) = boss {
+        if let Some(boss) = boss {
             r = Quat::default();
-            y = -42.0;
+            y = -boss.size * 0.5 - 2.0;
             x = 0.0;
         }
         commands.entity(ent).with_children(|hex| {
@@ -296,7 +513,8 @@ fn make_health_bar(mut commands: Commands, q_new: Query<(Entity, Option<&Boss>),
                     ..default()
                 },
                 ..default()
-            });
+            })
+            .insert(HealthBarBack);
         });
     }
 }
@@ -304,6 +522,7 @@ fn make_health_bar(mut commands: Commands, q_new: Query<(Entity, Option<&Boss>),
 fn animate_health_bar(
     mut commands: Commands,
     mut q_bar: Query<(Entity, &HealthBar, &Parent, &mut Sprite)>,
+    q_back: Query<(Entity, &Parent), With<HealthBarBack>>,
     mut q_piles: Query<&GoldPile>,
 ) {
     for (ent, bar, parent, mut sprite) in q_bar.iter_mut() {
@@ -319,12 +538,20 @@ fn animate_health_bar(
             },
         }
     }
+    // a tower's upgrade order takes its pile away but not the tower
+    for (ent, parent) in q_back.iter() {
+        if !q_piles.contains(parent.get()) {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
 }
 
 fn pile_input(
     input: Res<Input<KeyCode>>,
     mut ev_spawn: EventWriter<PileSpawnEvent>,
     mut ev_remove: EventWriter<PileRemoveEvent>,
+    mut ev_upgrade: EventWriter<PileUpgradeEvent>,
+    mut ev_withdraw: EventWriter<WithdrawGoldEvent>,
     q_selection: Query<(&Transform, &Hex), With<Selection>>,
 ) {
     for (_trans, hex) in q_selection.iter() {
@@ -334,6 +561,150 @@ fn pile_input(
         if input.just_pressed(KeyCode::G) {
             ev_spawn.send(PileSpawnEvent::new(hex.coords));
         }
+        if input.just_pressed(KeyCode::B) {
+            ev_upgrade.send(PileUpgradeEvent {
+                coords: hex.coords,
+                upgrade: PileUpgrade::Bank,
+            });
+        }
+        if input.just_pressed(KeyCode::V) {
+            ev_upgrade.send(PileUpgradeEvent {
+                coords: hex.coords,
+                upgrade: PileUpgrade::Vault,
+            });
+        }
+        if input.just_pressed(KeyCode::W) {
+            // hold shift to take a handful
+            let amount = if input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+                10
+            } else {
+                1
+            };
+            ev_withdraw.send(WithdrawGoldEvent {
+                coords: hex.coords,
+                amount,
+            });
+        }
+    }
+}
+
+// paid for out of the pile itself
+fn upgrade_pile(
+    mut commands: Commands,
+    mut ev_upgrade: EventReader<PileUpgradeEvent>,
+    mut q_piles: Query<(Entity, &Hex, &mut GoldPile, &mut Vault, Option<&Bank>)>,
+    mut ev_notice: EventWriter<NoticeEvent>,
+) {
+    for ev in ev_upgrade.iter() {
+        for (ent, hex, mut pile, mut vault, bank) in q_piles.iter_mut() {
+            if !ev.coords.is_same(hex.coords) {
+                continue;
+            }
+            match ev.upgrade {
+                PileUpgrade::Bank => {
+                    if bank.is_some() {
+                        break;
+                    }
+                    if pile.count >= BANK_COST {
+                        pile.count -= BANK_COST;
+                        commands.entity(ent).insert(Bank);
+                    } else {
+                        ev_notice.send(NoticeEvent(format!("Bank needs {} gold", BANK_COST)));
+                    }
+                }
+                PileUpgrade::Vault => {
+                    if let Some(&(cap, cost)) = VAULT_TIERS.get(vault.tier + 1) {
+                        if pile.count >= cost {
+                            pile.count -= cost;
+                            pile.gold_cap = cap;
+                            vault.tier += 1;
+                        } else {
+                            ev_notice.send(NoticeEvent(format!("Vault upgrade needs {} gold", cost)));
+                        }
+                    } else {
+                        ev_notice.send(NoticeEvent("Vault fully upgraded".to_string()));
+                    }
+                }
+            }
+            break;
+        }
+    }
+}
+
+fn pay_interest(
+    mut ev_wave: EventReader<WaveStartedEvent>,
+    mut q_banks: Query<&mut GoldPile, With<Bank>>,
+) {
+    for _ev in ev_wave.iter() {
+        for mut pile in q_banks.iter_mut() {
+            let interest = (pile.count as f32 * BANK_INTEREST) as u32;
+            pile.count = (pile.count + interest).min(pile.gold_cap);
+        }
+    }
+}
+
+fn withdraw_gold(
+    mut commands: Commands,
+    mut ev_withdraw: EventReader<WithdrawGoldEvent>,
+    mut q_piles: Query<(Entity, &Transform, &Hex, &mut GoldPile), With<Vault>>,
+    asset_server: Res<AssetServer>,
+    decay: Res<GoldDecaySettings>,
+) {
+    for ev in ev_withdraw.iter() {
+        for (ent, trans, hex, mut pile) in q_piles.iter_mut() {
+            if ev.coords.is_same(hex.coords) {
+                let amount = ev.amount.min(pile.count);
+                pile.count -= amount;
+                for _ in 0..amount {
+                    let gold = spawn_gold_bundle(
+                        &mut commands,
+                        &asset_server,
+                        trans.translation,
+                        decay.lifetime,
+                    );
+                    commands
+                        .entity(gold)
+                        .insert(Withdrawn { pile: ent })
+                        .insert(Banked);
+                }
+            }
+        }
+    }
+}
+
+fn clear_withdrawn(
+    mut commands: Commands,
+    q_gold: Query<(Entity, &Transform, &Withdrawn)>,
+    q_piles: Query<&Transform, With<GoldPile>>,
+) {
+    for (ent, trans, withdrawn) in q_gold.iter() {
+        let away = match q_piles.get(withdrawn.pile) {
+            Ok(pile_trans) => {
+                trans.translation.truncate().distance(pile_trans.translation.truncate()) > 30.0
+            }
+            Err(_) => true,
+        };
+        if away {
+            commands.entity(ent).remove::<Withdrawn>();
+        }
+    }
+}
+
+fn animate_stack(
+    mut q_stack: Query<(&Parent, &mut Transform, &mut Visibility, &mut Sprite), With<PileStack>>,
+    q_piles: Query<(&GoldPile, Option<&Bank>)>,
+) {
+    for (parent, mut trans, mut visibility, mut sprite) in q_stack.iter_mut() {
+        if let Ok((pile, bank)) = q_piles.get(parent.get()) {
+            visibility.is_visible = pile.count > 0;
+            let fill = (pile.count as f32).min(STACK_FULL) / STACK_FULL;
+            trans.scale = Vec3::ONE * (0.4 + fill * 1.2);
+            // banks are tinted so you can tell them apart
+            sprite.color = match bank {
+                Some(_) => LIME_GREEN,
+                None => Color::WHITE,
+            };
+        }
     }
 }
 
@@ -434,35 +805,51 @@ fn spawn_gold(
     mut commands: Commands,
     mut ev_gold_spawn: EventReader<SpawnGoldEvent>,
     asset_server: Res<AssetServer>,
+    decay: Res<GoldDecaySettings>,
 ) {
     for ev in ev_gold_spawn.iter() {
-        commands
-            .spawn_bundle(SpriteBundle {
-                texture: asset_server.load("sprites/gold2.png"),
-                // sprite: Sprite {
-                //     color: GOLD,
-                //     custom_size: Some(Vec2::new(8.0, 12.)),
-                //     ..default()
-                // },
-                transform: Transform {
-                    translation: Vec3 {
-                        x: ev.position.x,
-                        y: ev.position.y,
-                        z: 0.3,
-                    },
-                    scale: Vec3::ONE * 2.0,
-                    ..default()
+        spawn_gold_bundle(&mut commands, &asset_server, ev.position, decay.lifetime);
+    }
+}
+
+// returns the gold so callers can add to it
+pub fn spawn_gold_bundle(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec3,
+    lifetime: f32,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: asset_server.load("sprites/gold2.png"),
+            // sprite: Sprite {
+            //     color: GOLD,
+            //     custom_size: Some(Vec2::new(8.0, 12.)),
+            //     ..default()
+            // },
+            transform: Transform {
+                translation: Vec3 {
+                    x: position.x,
+                    y: position.y,
+                    z: 0.3,
                 },
+                scale: Vec3::ONE * 2.0,
                 ..default()
-            })
-            .insert(Gold)
-            .insert(Boid::new());
-    }
+            },
+            ..default()
+        })
+        .insert(Gold)
+        .insert(GoldAge::new(lifetime))
+        .insert(Boid::new())
+        .id()
 }
 
 fn check_mouse(
     mut commands: Commands,
-    q_gold: Query<(Entity, &Transform, Option<&MouseFollow>), With<Gold>>,
+    q_gold: Query<
+        (Entity, &Transform, Option<&MouseFollow>),
+        (With<Gold>, Without<OnConveyor>, Without<Courier>),
+    >,
     mouse: Res<MouseWorldPos>,
 ) {
     for (gold_ent, gold_trans, gold_follow) in q_gold.iter() {
@@ -485,13 +872,94 @@ fn check_mouse(
 }
 
 fn move_gold(
-    mut q_gold: Query<&mut Transform, (With<Gold>, With<MouseFollow>)>,
+    mut q_gold: Query<(Entity, &mut Transform), (With<Gold>, With<MouseFollow>)>,
+    index: Res<SpatialIndex>,
     mouse: Res<MouseWorldPos>,
     time: Res<Time>,
 ) {
-    for mut gold in q_gold.iter_mut() {
-        let dir = mouse.0 - gold.translation.truncate();
-        gold.translation +=
-            dir.normalize_or_zero().extend(0.0) * GOLD_MOVE_SPEED * time.delta_seconds();
+    // sorted so each piece keeps its place when others join or leave
+    let mut carried: Vec<Entity> = q_gold.iter().map(|(ent, _)| ent).collect();
+    carried.sort();
+    let n = carried.len();
+
+    let over_pile = index
+        .piles
+        .query(mouse.0, ORBIT_COLLAPSE_DIST)
+        .any(|(_, pos)| pos.distance(mouse.0) < ORBIT_COLLAPSE_DIST);
+    let radius = if over_pile || n <= 1 {
+        0.0
+    } else {
+        ORBIT_SPACING * (n as f32).sqrt()
+    };
+    let spin = time.seconds_since_startup() as f32 * ORBIT_SPIN;
+
+    for (i, ent) in carried.into_iter().enumerate() {
+        if let Ok((_, mut gold)) = q_gold.get_mut(ent) {
+            let angle = spin + i as f32 / n as f32 * std::f32::consts::TAU;
+            let slot = mouse.0 + Vec2::new(angle.cos(), angle.sin()) * radius;
+            // don't overshoot the slot and jitter around it
+            let step = (slot - gold.translation.truncate())
+                .clamp_length_max(GOLD_MOVE_SPEED * time.delta_seconds());
+            gold.translation += step.extend(0.0);
+        }
+    }
+}
+
+fn age_gold(
+    mut commands: Commands,
+    mut q_gold: Query<
+        (
+            Entity,
+            &Transform,
+            &mut GoldAge,
+            &mut Sprite,
+            Option<&MouseFollow>,
+            Option<&OnConveyor>,
+            Option<&Courier>,
+        ),
+        With<Gold>,
+    >,
+    mut q_hexes: Query<(&Transform, &mut Hex)>,
+    decay: Res<GoldDecaySettings>,
+    time: Res<Time>,
+) {
+    for (ent, trans, mut age, mut sprite, follow, conveyor, courier) in q_gold.iter_mut() {
+        if follow.is_some() || conveyor.is_some() || courier.is_some() {
+            // being carried resets it
+            age.timer.reset();
+            sprite.color.set_a(1.0);
+            continue;
+        }
+
+        if age.timer.tick(time.delta()).just_finished() {
+            if let DecayOutcome::ReturnToHex = decay.outcome {
+                let nearest = q_hexes.iter_mut().min_by_key(|(hex_trans, _)| {
+                    FloatOrd(Vec3::distance(hex_trans.translation, trans.translation))
+                });
+                if let Some((_, mut hex)) = nearest {
+                    hex.return_ore();
+                }
+            }
+            commands.entity(ent).despawn_recursive();
+        } else {
+            let left = age.timer.duration().as_secs_f32() - age.timer.elapsed_secs();
+            let alpha = (left / decay.fade_time).clamp(0.0, 1.0);
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+// the pile in the middle of the map pays for things
+// returns false if there isn't enough in it
+pub fn take_from_center_pile(q_piles: &mut Query<(&Hex, &mut GoldPile)>, amount: u32) -> bool {
+    for (hex, mut pile) in q_piles.iter_mut() {
+        if hex.coords.is_same(HexCoords::new()) {
+            if pile.count >= amount {
+                pile.count -= amount;
+                return true;
+            }
+            return false;
+        }
     }
+    false
 }
@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+
+use crate::{enemies::Dead, palette::*};
+
+const FLASH_TIME: f32 = 0.1;
+// fraction of knockback speed kept each second
+const KNOCKBACK_DECAY: f32 = 0.002;
+// armor can't block all of a hit
+const MIN_DAMAGE_FRACTION: f32 = 0.2;
+const BAR_WIDTH: f32 = 16.0;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_system(apply_damage)
+            .add_system(flash)
+            .add_system(knockback)
+            .add_system(make_health_bar)
+            .add_system(animate_health_bar);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum DamageType {
+    Kinetic,
+    Explosive,
+    Frost,
+}
+
+// fraction of each type that's ignored
+#[derive(Clone, Copy, Default)]
+pub struct Resistances {
+    pub kinetic: f32,
+    pub explosive: f32,
+    pub frost: f32,
+}
+
+impl Resistances {
+    fn against(&self, kind: DamageType) -> f32 {
+        match kind {
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Explosive => self.explosive,
+            DamageType::Frost => self.frost,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    // flat amount taken off every hit
+    pub armor: f32,
+    pub resist: Resistances,
+}
+
+impl Health {
+    pub fn new(max: f32, armor: f32, resist: Resistances) -> Self {
+        Health {
+            current: max,
+            max,
+            armor,
+            resist,
+        }
+    }
+
+    // how much a hit would actually do
+    pub fn damage_from(&self, amount: f32, kind: DamageType) -> f32 {
+        let after_armor = (amount - self.armor).max(amount * MIN_DAMAGE_FRACTION);
+        after_armor * (1.0 - self.resist.against(kind))
+    }
+}
+
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageType,
+    // direction and strength of the push
+    pub knockback: Vec2,
+}
+
+#[derive(Component)]
+struct HitFlash {
+    timer: Timer,
+    color: Color,
+}
+
+#[derive(Component)]
+struct Knockback(Vec2);
+
+#[derive(Component)]
+struct HealthBar {
+    width: f32,
+}
+
+#[derive(Component)]
+struct HealthBarBack;
+
+pub fn apply_damage(
+    mut commands: Commands,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut q_health: Query<(&mut Health, &mut Sprite, Option<&HitFlash>), Without<Dead>>,
+) {
+    for ev in ev_damage.iter() {
+        if let Ok((mut health, mut sprite, flash)) = q_health.get_mut(ev.target) {
+            if health.current <= 0.0 {
+                // already killed this frame
+                continue;
+            }
+            let damage = health.damage_from(ev.amount, ev.kind);
+            health.current -= damage;
+
+            // remember the real colour if it isn't already flashing
+            let color = match flash {
+                Some(flash) => flash.color,
+                None => sprite.color,
+            };
+            sprite.color = YELLOW_WHITE;
+            commands
+                .entity(ev.target)
+                .insert(HitFlash {
+                    timer: Timer::from_seconds(FLASH_TIME, false),
+                    color,
+                })
+                .insert(Knockback(ev.knockback));
+
+            if health.current <= 0.0 {
+                commands.entity(ev.target).insert(Dead);
+            }
+        }
+    }
+}
+
+fn flash(
+    mut commands: Commands,
+    mut q_flash: Query<(Entity, &mut HitFlash, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (ent, mut flash, mut sprite) in q_flash.iter_mut() {
+        if flash.timer.tick(time.delta()).just_finished() {
+            sprite.color = flash.color;
+            commands.entity(ent).remove::<HitFlash>();
+        }
+    }
+}
+
+fn knockback(
+    mut commands: Commands,
+    mut q_knockback: Query<(Entity, &mut Transform, &mut Knockback), Without<Dead>>,
+    time: Res<Time>,
+) {
+    for (ent, mut trans, mut push) in q_knockback.iter_mut() {
+        trans.translation += push.0.extend(0.0) * time.delta_seconds();
+        push.0 *= KNOCKBACK_DECAY.powf(time.delta_seconds());
+        if push.0.length_squared() < 1.0 {
+            commands.entity(ent).remove::<Knockback>();
+        }
+    }
+}
+
+// like the pile health bars
+// but only shows up once something's been hurt
+fn make_health_bar(mut commands: Commands, q_new: Query<(Entity, &Sprite), Added<Health>>) {
+    for (ent, sprite) in q_new.iter() {
+        let size = sprite.custom_size.unwrap_or(Vec2::new(BAR_WIDTH, BAR_WIDTH));
+        let width = size.x.max(BAR_WIDTH);
+        let y = size.y * 0.5 + 4.0;
+        commands.entity(ent).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: RED_PINK,
+                        custom_size: Some(Vec2::new(width, 3.0)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, y, 0.2),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(HealthBar { width });
+
+            parent
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: DARK_BLUE,
+                        custom_size: Some(Vec2::new(width + 2.0, 5.0)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, y, 0.1),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(HealthBarBack);
+        });
+    }
+}
+
+fn animate_health_bar(
+    mut q_bar: Query<
+        (&HealthBar, &Parent, &mut Sprite, &mut Transform, &mut Visibility),
+        Without<HealthBarBack>,
+    >,
+    mut q_back: Query<(&Parent, &mut Visibility), (With<HealthBarBack>, Without<HealthBar>)>,
+    q_health: Query<&Health, Changed<Health>>,
+) {
+    for (parent, mut visibility) in q_back.iter_mut() {
+        if let Ok(health) = q_health.get(parent.get()) {
+            visibility.is_visible = health.current < health.max;
+        }
+    }
+    for (bar, parent, mut sprite, mut trans, mut visibility) in q_bar.iter_mut() {
+        if let Ok(health) = q_health.get(parent.get()) {
+            visibility.is_visible = health.current < health.max;
+            let x = (health.current / health.max).clamp(0.0, 1.0);
+            sprite.custom_size = Some(Vec2::new(x * bar.width, 3.0));
+            // keep it lined up on the left
+            trans.translation.x = -(1.0 - x) * bar.width * 0.5;
+        }
+    }
+}
@@ -2,11 +2,23 @@ use bevy::{
     prelude::*,
     sprite::{collide_aabb::collide, MaterialMesh2dBundle},
 };
+use rand::prelude::*;
 
-use crate::{palette::*, MouseWorldPos};
+use crate::{palette::*, MouseWorldPos, RestartEvent};
 
 pub const DEG_TO_RAD: f32 = 0.01745;
 const HEX_SPACING: f32 = 0.86602540378;
+// rings around the center tile
+pub const GRID_RADIUS: u32 = 9;
+pub const HEX_RADIUS: f32 = 20.0;
+// how many hexes get a rich seam of gold
+const RICH_CHANCE: f64 = 0.15;
+const MAX_GOLD: u32 = 3;
+const RICH_MAX_GOLD: u32 = 5;
+// ore in a fresh hex
+const STARTING_ORE: u32 = 1;
+// center to the middle of an outside edge of the whole board
+const BOARD_APOTHEM: f32 = 1.5 * HEX_RADIUS * GRID_RADIUS as f32 + HEX_SPACING * HEX_RADIUS;
 
 pub struct HexPlugin;
 
@@ -17,7 +29,8 @@ impl Plugin for HexPlugin {
             .add_system(spawn_hex)
             .add_system(highlight_hex.before(select_hex))
             .add_system(select_hex)
-            .add_system(gather_gold);
+            .add_system(gather_gold)
+            .add_system(reset_hexes);
         //.add_system(colour_neighbours.after(highlight_hex));
         // the way selection is added and removed can mess these up
         // but I don't really know how to guarantee it.
@@ -39,17 +52,20 @@ pub struct Hex {
     // gold available to be mined
     pub gold: u32,
     max_gold: u32,
+    // rich ground holds more gold, decided when the board is made
+    rich: bool,
     // when gold increments
     timer: Timer,
 }
 
 impl Hex {
-    pub fn new(radius: f32, coords: HexCoords) -> Self {
+    pub fn new(radius: f32, coords: HexCoords, rich: bool) -> Self {
         Hex {
             radius,
             coords,
-            gold: 1,
-            max_gold: 3,
+            gold: STARTING_ORE,
+            max_gold: if rich { RICH_MAX_GOLD } else { MAX_GOLD },
+            rich,
             timer: Timer::from_seconds(7.5, true),
         }
     }
@@ -61,6 +77,17 @@ impl Hex {
         }
         return false;
     }
+
+    pub fn is_rich(&self) -> bool {
+        self.rich
+    }
+
+    // put a piece of gold back into the ground
+    pub fn return_ore(&mut self) {
+        if self.gold < self.max_gold {
+            self.gold += 1;
+        }
+    }
 }
 
 #[derive(Component)]
@@ -132,6 +159,16 @@ fn gather_gold(mut q_hexes: Query<&mut Hex>, time: Res<Time>) {
     }
 }
 
+// the ground goes back to how a new board starts
+fn reset_hexes(mut ev_restart: EventReader<RestartEvent>, mut q_hexes: Query<&mut Hex>) {
+    for _ev in ev_restart.iter() {
+        for mut hex in q_hexes.iter_mut() {
+            hex.gold = STARTING_ORE;
+            hex.timer.reset();
+        }
+    }
+}
+
 fn colour_neighbours(
     q_selection: Query<&Hex, With<Selection>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
@@ -152,7 +189,22 @@ fn colour_neighbours(
 }
 
 fn setup(ev_spawn: EventWriter<HexSpawnEvent>) {
-    spawn_hexes(ev_spawn, 9, 20., Vec2::ZERO);
+    spawn_hexes(ev_spawn, GRID_RADIUS, HEX_RADIUS, Vec2::ZERO);
+}
+
+// the board is one big hexagon with a point at the top
+// returns how far pos is past each edge (moved in by inset), pointing outwards
+pub fn past_board_edge(pos: Vec2, inset: f32) -> Vec2 {
+    let mut past = Vec2::ZERO;
+    for i in 0..6 {
+        let angle = 60.0 * i as f32 * DEG_TO_RAD;
+        let normal = Vec2::new(angle.cos(), angle.sin());
+        let d = pos.dot(normal) - (BOARD_APOTHEM - inset);
+        if d > 0.0 {
+            past += normal * d;
+        }
+    }
+    past
 }
 
 fn spawn_hexes(
@@ -273,8 +325,10 @@ fn spawn_hex(
     mut ev_spawn: EventReader<HexSpawnEvent>,
     asset_server: Res<AssetServer>,
 ) {
+    let mut rng = rand::thread_rng();
     for ev in ev_spawn.iter() {
         let (position, radius, coords) = (ev.position, ev.radius, ev.coords);
+        let rich = rng.gen_bool(RICH_CHANCE);
 
         //println!("Spawn: {:?}", coords);
 
@@ -288,7 +342,7 @@ fn spawn_hex(
                     .with_rotation(Quat::from_rotation_z(30.0 * DEG_TO_RAD)),
                 ..default()
             })
-            .insert(Hex::new(radius, coords));
+            .insert(Hex::new(radius, coords, rich));
         // .with_children(|parent| {
         //     parent.spawn_bundle(SpriteBundle {
         //         texture: asset_server.load("sprites/HexGrass.png"),
@@ -309,6 +363,11 @@ fn spawn_hex(
 
         commands.spawn_bundle(SpriteBundle {
             texture: asset_server.load("sprites/HexGrass.png"),
+            // rich ground shows through as a gold tint
+            sprite: Sprite {
+                color: if rich { GOLD } else { Color::WHITE },
+                ..default()
+            },
             transform: Transform::from_translation(position.extend(0.15)),
             // transform: Transform {
             //     // spawn on top of the underlying hex
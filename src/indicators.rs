@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+
+use crate::{boss::Boss, enemies::Enemy, palette::*};
+
+// keeps markers off the very edge of the screen
+const EDGE_MARGIN: f32 = 14.0;
+const BLINK_SPEED: f32 = 6.0;
+// how long a notice stays up
+const NOTICE_TIME: f32 = 3.0;
+
+pub struct IndicatorPlugin;
+
+impl Plugin for IndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NoticeEvent>()
+            .add_startup_system(spawn_notice_label)
+            .add_system(make_arrows)
+            .add_system(update_arrows)
+            .add_system(place_telegraphs)
+            .add_system(show_notices);
+    }
+}
+
+// points at its parent when the parent is off screen
+#[derive(Component)]
+struct EdgeArrow;
+
+// where enemies will show up next wave
+#[derive(Component)]
+pub struct SpawnTelegraph {
+    target: Vec2,
+}
+
+// tells the player why something they tried didn't work
+pub struct NoticeEvent(pub String);
+
+#[derive(Component)]
+struct NoticeLabel {
+    timer: Timer,
+}
+
+// the camera never moves and shows 720 units tall
+fn screen_half_size(windows: &Windows) -> Vec2 {
+    let aspect = windows
+        .get_primary()
+        .map(|win| win.width() / win.height())
+        .unwrap_or(crate::WIDTH / crate::HEIGHT);
+    Vec2::new(360.0 * aspect, 360.0)
+}
+
+fn on_screen(pos: Vec2, half: Vec2) -> bool {
+    pos.x.abs() < half.x && pos.y.abs() < half.y
+}
+
+// where a line from the middle of the screen towards pos leaves the screen
+fn edge_point(pos: Vec2, half: Vec2) -> Vec2 {
+    let inner = half - Vec2::splat(EDGE_MARGIN);
+    let scale_x = if pos.x.abs() > 0.001 {
+        inner.x / pos.x.abs()
+    } else {
+        f32::MAX
+    };
+    let scale_y = if pos.y.abs() > 0.001 {
+        inner.y / pos.y.abs()
+    } else {
+        f32::MAX
+    };
+    pos * scale_x.min(scale_y)
+}
+
+pub fn spawn_telegraph(commands: &mut Commands, target: Vec2, count: usize) -> Entity {
+    // bigger for more enemies
+    let size = 8.0 + (count as f32).sqrt() * 3.0;
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: RED_PINK,
+                custom_size: Some(Vec2::new(size, size)),
+                ..default()
+            },
+            transform: Transform {
+                translation: target.extend(0.9),
+                // diamond
+                rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(SpawnTelegraph { target })
+        .id()
+}
+
+fn place_telegraphs(
+    mut q_telegraphs: Query<(&SpawnTelegraph, &mut Transform, &mut Sprite)>,
+    windows: Res<Windows>,
+    time: Res<Time>,
+) {
+    let half = screen_half_size(&windows);
+    let blink = (time.seconds_since_startup() as f32 * BLINK_SPEED).sin() * 0.5 + 0.5;
+    for (telegraph, mut trans, mut sprite) in q_telegraphs.iter_mut() {
+        let pos = if on_screen(telegraph.target, half) {
+            telegraph.target
+        } else {
+            edge_point(telegraph.target, half)
+        };
+        trans.translation.x = pos.x;
+        trans.translation.y = pos.y;
+        sprite.color.set_a(0.3 + 0.7 * blink);
+    }
+}
+
+fn make_arrows(mut commands: Commands, q_new: Query<Entity, Or<(Added<Enemy>, Added<Boss>)>>) {
+    for ent in q_new.iter() {
+        commands.entity(ent).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: RED_PINK,
+                        custom_size: Some(Vec2::new(10.0, 4.0)),
+                        ..default()
+                    },
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(EdgeArrow);
+        });
+    }
+}
+
+// carriers always get one, pointing where they'll leave
+fn update_arrows(
+    mut q_arrows: Query<(&Parent, &mut Transform, &mut Visibility, &mut Sprite), With<EdgeArrow>>,
+    q_targets: Query<(&Transform, Option<&Enemy>), Without<EdgeArrow>>,
+    windows: Res<Windows>,
+) {
+    let half = screen_half_size(&windows);
+    for (parent, mut trans, mut visibility, mut sprite) in q_arrows.iter_mut() {
+        let (target_trans, enemy) = match q_targets.get(parent.get()) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        let pos = target_trans.translation.truncate();
+        let carrying = enemy.map_or(false, |enemy| enemy.has_gold());
+
+        if on_screen(pos, half) && !carrying {
+            visibility.is_visible = false;
+            continue;
+        }
+        visibility.is_visible = true;
+
+        let edge = edge_point(pos, half);
+        // the arrow is a child so undo the parent's position
+        let local = edge - pos;
+        trans.translation = local.extend(0.5);
+        // carriers on screen point outwards, everything else points at its enemy
+        let dir = if on_screen(pos, half) {
+            edge - pos
+        } else {
+            pos - edge
+        };
+        trans.rotation = Quat::from_rotation_z(dir.y.atan2(dir.x));
+
+        if carrying {
+            sprite.color = GOLD;
+            sprite.custom_size = Some(Vec2::new(16.0, 6.0));
+        } else {
+            sprite.color = RED_PINK;
+            sprite.custom_size = Some(Vec2::new(10.0, 4.0));
+        }
+    }
+}
+
+fn spawn_notice_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: ORANGE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(NoticeLabel {
+            timer: Timer::from_seconds(NOTICE_TIME, false),
+        });
+}
+
+// the newest notice replaces the last and clears itself
+fn show_notices(
+    mut ev_notice: EventReader<NoticeEvent>,
+    mut q_label: Query<(&mut Text, &mut NoticeLabel)>,
+    time: Res<Time>,
+) {
+    let newest = ev_notice.iter().last();
+    for (mut text, mut label) in q_label.iter_mut() {
+        if let Some(notice) = newest {
+            text.sections[0].value = notice.0.clone();
+            label.timer.reset();
+        } else if label.timer.tick(time.delta()).just_finished() {
+            text.sections[0].value.clear();
+        }
+    }
+}
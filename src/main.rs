@@ -17,13 +17,24 @@ use palette::*; //{DARK_BLUE, LIGHT_BLUE, DARK_ORANGE, BLUE};
 use std::env;
 
 mod boids;
+mod boss;
+mod conveyor;
+mod difficulty;
 mod enemies;
 mod gold;
+mod health;
 mod hexes;
+mod indicators;
 mod input;
 mod palette;
+mod spatial;
+mod stats;
+mod steering;
+mod synergy;
 mod tower;
+mod transfer;
 mod tutorial;
+mod waves;
 
 fn main() {
     //env::set_var("RUST_BACKTRACE", "1");
@@ -34,7 +45,18 @@ fn main() {
         .add_plugin(tower::TowerPlugin)
         .add_plugin(gold::GoldPlugin)
         .add_plugin(enemies::EnemyPlugin)
+        .add_plugin(boss::BossPlugin)
+        .add_plugin(health::HealthPlugin)
+        .add_plugin(waves::WavePlugin)
+        .add_plugin(indicators::IndicatorPlugin)
         .add_plugin(boids::BoidsPlugin)
+        .add_plugin(steering::SteeringPlugin)
+        .add_plugin(conveyor::ConveyorPlugin)
+        .add_plugin(spatial::SpatialPlugin)
+        .add_plugin(transfer::TransferPlugin)
+        .add_plugin(stats::StatsPlugin)
+        .add_plugin(difficulty::DifficultyPlugin)
+        .add_plugin(synergy::SynergyPlugin)
         .add_plugin(tutorial::TutorialPlugin)
         .insert_resource(MouseWorldPos(Vec2::ONE * 10000.0))
         .insert_resource(WindowDescriptor {
@@ -45,6 +67,8 @@ fn main() {
             ..Default::default()
         })
         .add_event::<StartSpawningEnemiesEvent>()
+        .add_event::<GameOverEvent>()
+        .add_event::<RestartEvent>()
         .add_startup_system(setup)
         .add_startup_system(set_window_icon)
         .add_system(update_mouse_position)
@@ -58,6 +82,20 @@ struct MouseWorldPos(Vec2);
 
 pub struct StartSpawningEnemiesEvent;
 
+pub enum LossReason {
+    CenterPileEmptied,
+    TooMuchStolen,
+    OutOfLives,
+}
+
+pub struct GameOverEvent {
+    pub reason: LossReason,
+}
+
+// every plugin clears out its own stuff
+// and the center pile goes back to how it started
+pub struct RestartEvent;
+
 fn setup(mut commands: Commands) {
     commands.spawn_bundle(Camera2dBundle {
         projection: OrthographicProjection {
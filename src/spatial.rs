@@ -0,0 +1,107 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    conveyor::OnConveyor,
+    enemies::{Dead, Enemy},
+    gold::{Gold, GoldPile, MouseFollow},
+    transfer::Courier,
+};
+
+// about one hex across
+const CELL_SIZE: f32 = 40.0;
+
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        // rebuilt before any of the Update systems look at it
+        // so everything in a frame sees the same buckets
+        app.insert_resource(SpatialIndex::default())
+            .add_system_to_stage(CoreStage::PreUpdate, rebuild_index);
+    }
+}
+
+// buckets entities by position so collision checks
+// only look at things that are nearby
+#[derive(Default)]
+pub struct SpatialGrid {
+    cells: HashMap<(i32, i32), Vec<(Entity, Vec2)>>,
+}
+
+impl SpatialGrid {
+    fn clear(&mut self) {
+        // keep the allocations around for next frame
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+    }
+
+    fn insert(&mut self, ent: Entity, pos: Vec2) {
+        self.cells
+            .entry(cell_of(pos))
+            .or_insert_with(Vec::new)
+            .push((ent, pos));
+    }
+
+    // everything in the cells the circle touches
+    // callers still need to do their own exact check
+    pub fn query(&self, pos: Vec2, radius: f32) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let (min_x, min_y) = cell_of(pos - Vec2::splat(radius));
+        let (max_x, max_y) = cell_of(pos + Vec2::splat(radius));
+        (min_x..=max_x)
+            .flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flat_map(|cell| cell.iter().copied())
+    }
+}
+
+fn cell_of(pos: Vec2) -> (i32, i32) {
+    (
+        (pos.x / CELL_SIZE).floor() as i32,
+        (pos.y / CELL_SIZE).floor() as i32,
+    )
+}
+
+#[derive(Default)]
+pub struct SpatialIndex {
+    pub gold: SpatialGrid,
+    // gold that isn't following the mouse, on a conveyor or in a transfer
+    pub loose_gold: SpatialGrid,
+    pub piles: SpatialGrid,
+    pub enemies: SpatialGrid,
+}
+
+fn rebuild_index(
+    mut index: ResMut<SpatialIndex>,
+    q_gold: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&MouseFollow>,
+            Option<&OnConveyor>,
+            Option<&Courier>,
+        ),
+        With<Gold>,
+    >,
+    q_piles: Query<(Entity, &Transform), With<GoldPile>>,
+    q_enemies: Query<(Entity, &Transform), (With<Enemy>, Without<Dead>)>,
+) {
+    index.gold.clear();
+    index.loose_gold.clear();
+    index.piles.clear();
+    index.enemies.clear();
+
+    for (ent, trans, follow, conveyor, courier) in q_gold.iter() {
+        let pos = trans.translation.truncate();
+        index.gold.insert(ent, pos);
+        if follow.is_none() && conveyor.is_none() && courier.is_none() {
+            index.loose_gold.insert(ent, pos);
+        }
+    }
+    for (ent, trans) in q_piles.iter() {
+        index.piles.insert(ent, trans.translation.truncate());
+    }
+    for (ent, trans) in q_enemies.iter() {
+        index.enemies.insert(ent, trans.translation.truncate());
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::{
+    enemies::{Dead, Enemy},
+    tutorial::AcceptInput,
+    waves::WaveClearedEvent,
+    GameOverEvent, LossReason, RestartEvent,
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunStats::default())
+            .insert_resource(LossRules::default())
+            .add_system(count_kills)
+            .add_system(count_waves)
+            .add_system(tick_time)
+            .add_system(check_loss)
+            .add_system(end_run)
+            .add_system(reset_stats);
+    }
+}
+
+#[derive(Default)]
+pub struct RunStats {
+    // carried off the edge by enemies
+    pub gold_stolen: u32,
+    // stored in player piles
+    pub gold_banked: u32,
+    pub enemies_killed: u32,
+    pub waves_cleared: u32,
+    // enemies that got away with gold
+    pub escaped: u32,
+    // a raid took the last of the center pile
+    pub center_emptied: bool,
+    pub time: f32,
+    pub over: bool,
+}
+
+// any of these being hit ends the run
+// None turns a rule off
+// set from the difficulty
+pub struct LossRules {
+    pub max_stolen: Option<u32>,
+    pub center_pile_empty: bool,
+    // every enemy that escapes with gold costs one
+    pub lives: Option<u32>,
+}
+
+impl Default for LossRules {
+    fn default() -> Self {
+        LossRules {
+            max_stolen: Some(100),
+            // the center pile starts small, one raid would end it
+            center_pile_empty: false,
+            lives: None,
+        }
+    }
+}
+
+fn count_kills(q_dead: Query<(), (With<Enemy>, Added<Dead>)>, mut stats: ResMut<RunStats>) {
+    for _ in q_dead.iter() {
+        stats.enemies_killed += 1;
+    }
+}
+
+fn count_waves(mut ev_cleared: EventReader<WaveClearedEvent>, mut stats: ResMut<RunStats>) {
+    for _ev in ev_cleared.iter() {
+        stats.waves_cleared += 1;
+    }
+}
+
+fn tick_time(mut stats: ResMut<RunStats>, accept: Res<AcceptInput>, time: Res<Time>) {
+    // only while actually playing
+    if accept.0 && !stats.over {
+        stats.time += time.delta_seconds();
+    }
+}
+
+fn check_loss(
+    stats: Res<RunStats>,
+    rules: Res<LossRules>,
+    mut ev_game_over: EventWriter<GameOverEvent>,
+) {
+    if stats.over {
+        return;
+    }
+    if rules.center_pile_empty && stats.center_emptied {
+        ev_game_over.send(GameOverEvent {
+            reason: LossReason::CenterPileEmptied,
+        });
+        return;
+    }
+    if let Some(max) = rules.max_stolen {
+        if stats.gold_stolen >= max {
+            ev_game_over.send(GameOverEvent {
+                reason: LossReason::TooMuchStolen,
+            });
+            return;
+        }
+    }
+    if let Some(lives) = rules.lives {
+        if stats.escaped >= lives {
+            ev_game_over.send(GameOverEvent {
+                reason: LossReason::OutOfLives,
+            });
+        }
+    }
+}
+
+fn end_run(mut ev_game_over: EventReader<GameOverEvent>, mut stats: ResMut<RunStats>) {
+    for _ev in ev_game_over.iter() {
+        stats.over = true;
+    }
+}
+
+fn reset_stats(mut ev_restart: EventReader<RestartEvent>, mut stats: ResMut<RunStats>) {
+    for _ev in ev_restart.iter() {
+        *stats = RunStats::default();
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+
+use crate::{
+    enemies::{move_enemies, Dead, Enemy},
+    hexes::HEX_RADIUS,
+    spatial::SpatialIndex,
+    tower::Tower,
+};
+
+// how far a death spooks the others
+const SCATTER_RADIUS: f32 = 80.0;
+// walking enemies keep this far from a tower's center
+pub const TOWER_CLEARANCE: f32 = HEX_RADIUS * 1.5;
+// fraction of the scatter push left after a second
+const SCATTER_DECAY: f32 = 0.05;
+const SQUAD_RADIUS: f32 = 60.0;
+const SQUAD_SIZE: usize = 4;
+// stop waiting for the rest of the squad after this long
+const REGROUP_TIME: f32 = 3.0;
+const ESCORT_RADIUS: f32 = 90.0;
+// escorts sit a bit ahead of the carrier
+const ESCORT_AHEAD: f32 = 20.0;
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(scatter_on_death.before(steer_enemies))
+            .add_system(steer_enemies.before(move_enemies));
+    }
+}
+
+// how much each behaviour counts for a kind of enemy
+// boid separation still gets applied on top
+#[derive(Clone, Copy)]
+pub struct SteeringWeights {
+    pub seek: f32,
+    // keeping out of tower range
+    pub avoid_towers: f32,
+    // never gets closer than this to a tower, whatever avoid_towers is
+    pub tower_clearance: f32,
+    pub scatter: f32,
+    pub regroup: f32,
+    pub escort: f32,
+}
+
+#[derive(Component)]
+pub struct Steering {
+    weights: SteeringWeights,
+    // everything but seeking, added up each frame
+    pub force: Vec2,
+    // how much going for gold counts this frame
+    pub seek: f32,
+    scatter: Vec2,
+    regroup_timer: Timer,
+    grouped: bool,
+}
+
+impl Steering {
+    pub fn new(weights: SteeringWeights) -> Self {
+        Steering {
+            weights,
+            force: Vec2::ZERO,
+            seek: weights.seek,
+            scatter: Vec2::ZERO,
+            regroup_timer: Timer::from_seconds(REGROUP_TIME, false),
+            // nothing to wait for
+            grouped: weights.regroup <= 0.0,
+        }
+    }
+}
+
+fn scatter_on_death(
+    q_dead: Query<&Transform, (With<Enemy>, Added<Dead>)>,
+    mut q_enemies: Query<&mut Steering, Without<Dead>>,
+    index: Res<SpatialIndex>,
+) {
+    for dead_trans in q_dead.iter() {
+        let dead_pos = dead_trans.translation.truncate();
+        for (ent, pos) in index.enemies.query(dead_pos, SCATTER_RADIUS) {
+            let d = pos.distance(dead_pos);
+            if d > SCATTER_RADIUS {
+                continue;
+            }
+            if let Ok(mut steering) = q_enemies.get_mut(ent) {
+                let away = (pos - dead_pos).normalize_or_zero();
+                steering.scatter += away * (1.0 - d / SCATTER_RADIUS);
+            }
+        }
+    }
+}
+
+fn steer_enemies(
+    mut q_enemies: Query<(Entity, &Transform, &Enemy, &mut Steering), Without<Dead>>,
+    q_towers: Query<(&Transform, &Tower), Without<Enemy>>,
+    index: Res<SpatialIndex>,
+    time: Res<Time>,
+) {
+    // carriers head straight out from the middle
+    let carriers: Vec<(Entity, Vec2)> = q_enemies
+        .iter()
+        .filter(|(_, _, enemy, _)| enemy.has_gold())
+        .map(|(ent, trans, _, _)| (ent, trans.translation.truncate()))
+        .collect();
+
+    for (ent, trans, enemy, mut steering) in q_enemies.iter_mut() {
+        let pos = trans.translation.truncate();
+        let w = steering.weights;
+        let mut force = Vec2::ZERO;
+        let mut seek = w.seek;
+
+        if w.avoid_towers > 0.0 || w.tower_clearance > 0.0 {
+            for (t_trans, tower) in q_towers.iter() {
+                let away = pos - t_trans.translation.truncate();
+                let d = away.length();
+                let mut push = 0.0;
+                if d < tower.range() {
+                    // stronger the further inside
+                    push += (1.0 - d / tower.range()) * w.avoid_towers;
+                }
+                if d < w.tower_clearance {
+                    // walks around the tower itself
+                    push += 1.0 - d / w.tower_clearance;
+                }
+                force += away.normalize_or_zero() * push;
+            }
+        }
+
+        force += steering.scatter * w.scatter;
+        steering.scatter *= SCATTER_DECAY.powf(time.delta_seconds());
+
+        if !enemy.has_gold() {
+            // only forms the squad before it sets off
+            // boids cohesion keeps it together after that
+            if !steering.grouped {
+                let mut count = 1;
+                let mut center = pos;
+                for (other, other_pos) in index.enemies.query(pos, SQUAD_RADIUS) {
+                    if other != ent && other_pos.distance(pos) < SQUAD_RADIUS {
+                        count += 1;
+                        center += other_pos;
+                    }
+                }
+                center /= count as f32;
+
+                let done = steering.regroup_timer.tick(time.delta()).finished();
+                if count >= SQUAD_SIZE || done {
+                    steering.grouped = true;
+                } else {
+                    force += (center - pos).normalize_or_zero() * w.regroup;
+                    // hang around until the squad is together
+                    seek *= 0.2;
+                }
+            }
+
+            if w.escort > 0.0 {
+                let carrier = carriers
+                    .iter()
+                    .filter(|(other, other_pos)| {
+                        *other != ent && other_pos.distance(pos) < ESCORT_RADIUS
+                    })
+                    .map(|(_, other_pos)| *other_pos)
+                    .next();
+                if let Some(carrier_pos) = carrier {
+                    let ahead = carrier_pos + carrier_pos.normalize_or_zero() * ESCORT_AHEAD;
+                    force += (ahead - pos).normalize_or_zero() * w.escort;
+                    seek *= 0.5;
+                }
+            }
+        }
+
+        steering.force = force;
+        steering.seek = seek;
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use crate::{
+    difficulty::Difficulty,
+    gold::GoldSpawner,
+    hexes::{Hex, HexCoords},
+    tower::{Tower, TowerKind},
+};
+
+// neighbours don't change often, no need to check every frame
+const SYNERGY_CHECK_TIME: f32 = 0.5;
+
+pub struct SynergyPlugin;
+
+impl Plugin for SynergyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SynergyTimer(Timer::from_seconds(SYNERGY_CHECK_TIME, true)))
+            .add_system(apply_synergies);
+    }
+}
+
+// what a neighbouring hex needs for a rule to count
+#[derive(Clone, Copy, Debug)]
+pub enum Neighbour {
+    Tower(TowerKind),
+    // a hex with a rich seam of gold, see Hex::is_rich
+    RichGround,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Bonus {
+    Range,
+    MineRate,
+}
+
+// part of a tower's stats
+// every matching neighbour adds amount to the bonus
+#[derive(Clone, Copy, Debug)]
+pub struct Synergy {
+    pub from: Neighbour,
+    pub bonus: Bonus,
+    pub amount: f32,
+    // shown on hover
+    pub label: &'static str,
+}
+
+// attack towers see further next to a radar
+pub const RADAR_RANGE: Synergy = Synergy {
+    from: Neighbour::Tower(TowerKind::Radar),
+    bonus: Bonus::Range,
+    amount: 0.2,
+    label: "radar",
+};
+
+// miners next to each other share the work
+pub const SHARED_MINING: Synergy = Synergy {
+    from: Neighbour::Tower(TowerKind::Miner),
+    bonus: Bonus::MineRate,
+    amount: 0.25,
+    label: "miners",
+};
+
+pub const RICH_GROUND: Synergy = Synergy {
+    from: Neighbour::RichGround,
+    bonus: Bonus::MineRate,
+    amount: 0.1,
+    label: "rich hexes",
+};
+
+// fractions on top of the tower's own stats
+#[derive(Default)]
+pub struct TowerBonus {
+    pub range: f32,
+    pub mine_rate: f32,
+    // one line per rule that applies, shown on hover
+    pub notes: Vec<String>,
+}
+
+struct SynergyTimer(Timer);
+
+fn apply_synergies(
+    mut q_hexes: Query<(&Hex, Option<&mut Tower>, Option<&mut GoldSpawner>)>,
+    mut timer: ResMut<SynergyTimer>,
+    difficulty: Res<Difficulty>,
+    time: Res<Time>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    // (coords, built tower, rich)
+    let board: Vec<(HexCoords, Option<TowerKind>, bool)> = q_hexes
+        .iter()
+        .map(|(hex, tower, _)| (hex.coords, tower.map(|t| t.kind), hex.is_rich()))
+        .collect();
+
+    for (hex, tower, spawner) in q_hexes.iter_mut() {
+        let mut tower = match tower {
+            Some(tower) => tower,
+            None => continue,
+        };
+        let neighbours: Vec<&(HexCoords, Option<TowerKind>, bool)> = hex
+            .coords
+            .get_neighbours()
+            .iter()
+            .filter_map(|n| board.iter().find(|(coords, _, _)| n.is_same(*coords)))
+            .collect();
+
+        let mut bonus = TowerBonus::default();
+        for synergy in tower.kind.stats().synergies {
+            let count = neighbours
+                .iter()
+                .filter(|(_, kind, rich)| match synergy.from {
+                    Neighbour::Tower(want) => *kind == Some(want),
+                    Neighbour::RichGround => *rich,
+                })
+                .count();
+            if count == 0 {
+                continue;
+            }
+            let amount = synergy.amount * count as f32;
+            let name = match synergy.bonus {
+                Bonus::Range => {
+                    bonus.range += amount;
+                    "range"
+                }
+                Bonus::MineRate => {
+                    bonus.mine_rate += amount;
+                    "mining"
+                }
+            };
+            bonus.notes.push(format!(
+                "+{:.0}% {} from {} {}",
+                amount * 100.0,
+                name,
+                count,
+                synergy.label
+            ));
+        }
+        tower.bonus = bonus;
+
+        if let (Some(mut spawner), Some(spawn_time)) = (spawner, tower.spawn_time(&difficulty)) {
+            spawner.set_spawn_time(spawn_time);
+        }
+    }
+}
@@ -1,15 +1,52 @@
-use bevy::{prelude::*, sprite::collide_aabb::collide, utils::FloatOrd};
+use bevy::{
+    prelude::*,
+    sprite::collide_aabb::collide,
+    utils::{FloatOrd, HashMap},
+};
+use std::{
+    f32::consts::{PI, TAU},
+    time::Duration,
+};
 
 use crate::{
-    enemies::{BossSpawnEvent, Dead, Enemy},
+    boids::Boid,
+    boss::Boss,
+    difficulty::Difficulty,
+    enemies::{Chilled, Dead, Enemy},
     gold::*,
+    health::{apply_damage, DamageEvent, DamageType, Health},
     hexes::*,
     palette::*,
-    tutorial::AcceptInput,
+    spatial::SpatialIndex,
+    synergy::{Synergy, TowerBonus, RADAR_RANGE, RICH_GROUND, SHARED_MINING},
+    tutorial::{AcceptInput, ButtonInfo, KeepMenu},
+    RestartEvent, StartSpawningEnemiesEvent,
 };
 
-const TOWER_COST_GROWTH: u32 = 2;
-const TOWERS_TO_SPAWN_BOSS: u32 = 10; //10
+const BULLET_KNOCKBACK: f32 = 80.0;
+// radians a second
+const HOMING_TURN: f32 = 6.0;
+// enemies a piercing shot can go through after the first
+const PIERCE_COUNT: usize = 2;
+// the edge of a splash does this much less than the middle
+const SPLASH_FALLOFF: f32 = 0.75;
+// magnets stop pulling once gold is this close
+const MAGNET_HOLD: f32 = 12.0;
+const MAGNET_SPEED: f32 = 80.0;
+const BUILD_BUTTON_WIDTH: f32 = 150.0;
+const BUILD_BUTTON_HEIGHT: f32 = 32.0;
+// fraction of what was paid in that comes back when it's removed
+const REFUND_RATE: f32 = 0.8;
+const MAX_LEVEL: u32 = 3;
+// each level up multiplies these
+const LEVEL_RANGE: f32 = 1.15;
+const LEVEL_FIRE_TIME: f32 = 0.8;
+const LEVEL_DAMAGE: f32 = 1.5;
+// the sprite gets this much bigger and lighter
+const LEVEL_SCALE: f32 = 0.15;
+const LEVEL_TINT: f32 = 0.35;
+// seconds to press X again on a mostly paid for preview
+const CANCEL_CONFIRM_TIME: f32 = 3.0;
 pub struct TowerPlugin;
 
 impl Plugin for TowerPlugin {
@@ -19,47 +56,305 @@ impl Plugin for TowerPlugin {
             .add_event::<SpawnBulletEvent>()
             //.add_system(spawn_tower)
             //.add_system(tower_input)
-            .insert_resource(TowerSpawnCost { cost: 5 })
-            .insert_resource(TowerCount {
-                count: 0,
-                boss_spawned: false,
-            })
+            .insert_resource(TowerSpawnCost::default())
+            .insert_resource(IncomingDamage::default())
+            .insert_resource(CancelConfirm::default())
             .add_system(tower_mouse_input)
+            .add_system(cycle_target_mode)
+            .add_startup_system(spawn_tower_label)
+            .add_system(show_tower_info.after(cycle_target_mode))
+            .add_system(build_menu_buttons)
             .add_system(spawn_tower_preview)
             .add_system(preview_paid_for)
+            .add_system(order_upgrade)
+            .add_system(upgrade_paid_for)
             .add_system(remove_tower)
             .add_system(tower_shoot)
+            .add_system(magnet_pull)
             .add_system(spawn_bullet)
             .add_system(tick_bullet)
             .add_system(move_bullet)
-            .add_system(bullet_hit);
+            .add_system(bullet_hit.before(apply_damage))
+            .add_system(reset_towers)
+            .add_system(fix_cost_growth.after(reset_towers));
         //.add_system(rotate_sprite);
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TowerKind {
+    Basic,
+    // long range, slow and hits hard
+    Sniper,
+    // shells hurt everything where they land
+    Mortar,
+    // slows what it hits
+    Frost,
+    // pulls loose gold in towards it
+    Magnet,
+    // doesn't shoot, makes gold faster
+    Miner,
+    // doesn't shoot, towers next to it get more range
+    Radar,
+}
+
+pub struct TowerStats {
+    pub name: &'static str,
+    // shown when hovering its build button
+    pub blurb: &'static str,
+    // once it's built
+    pub texture: &'static str,
+    // while it's waiting for gold
+    pub preview_texture: &'static str,
+    pub tint: Color,
+    pub start_cost: u32,
+    // multiplies the difficulty's cost growth
+    pub cost_growth: f32,
+    pub fire_time: f32,
+    pub range: f32,
+    // towers that don't shoot have none
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub projectile: Projectile,
+    pub bullet_speed: f32,
+    // shells hurt everything this close to where they land
+    pub splash: f32,
+    // speed multiplier on anything it hits, 1 is no slow
+    pub slow: f32,
+    pub magnet: bool,
+    // multiplies the gold spawn time, None doesn't make gold
+    pub mines: Option<f32>,
+    // bonuses from what's on the hexes around it
+    pub synergies: &'static [Synergy],
+}
+
+impl TowerKind {
+    pub const ALL: [TowerKind; 7] = [
+        TowerKind::Basic,
+        TowerKind::Sniper,
+        TowerKind::Mortar,
+        TowerKind::Frost,
+        TowerKind::Magnet,
+        TowerKind::Miner,
+        TowerKind::Radar,
+    ];
+
+    pub fn stats(self) -> TowerStats {
+        let basic = TowerStats {
+            name: "Tower",
+            blurb: "Shoots and mines",
+            texture: "sprites/Tower.png",
+            preview_texture: "sprites/UnbuiltTower.png",
+            tint: Color::WHITE,
+            start_cost: 5,
+            cost_growth: 1.0,
+            fire_time: 1.0,
+            range: 200.0,
+            damage: 1.0,
+            damage_type: DamageType::Kinetic,
+            projectile: Projectile::Homing,
+            bullet_speed: 400.0,
+            splash: 0.0,
+            slow: 1.0,
+            magnet: false,
+            mines: Some(1.0),
+            synergies: &[RADAR_RANGE, RICH_GROUND],
+        };
+        match self {
+            TowerKind::Basic => basic,
+            TowerKind::Sniper => TowerStats {
+                name: "Sniper",
+                blurb: "Long range",
+                texture: "sprites/SniperTower.png",
+                preview_texture: "sprites/UnbuiltSniperTower.png",
+                tint: LIGHT_BLUE,
+                start_cost: 8,
+                cost_growth: 1.5,
+                fire_time: 2.5,
+                range: 350.0,
+                damage: 4.0,
+                projectile: Projectile::Piercing,
+                bullet_speed: 700.0,
+                mines: None,
+                synergies: &[RADAR_RANGE],
+                ..basic
+            },
+            TowerKind::Mortar => TowerStats {
+                name: "Mortar",
+                blurb: "Hits groups",
+                texture: "sprites/MortarTower.png",
+                preview_texture: "sprites/UnbuiltMortarTower.png",
+                tint: ORANGE,
+                start_cost: 10,
+                cost_growth: 2.0,
+                fire_time: 2.0,
+                range: 180.0,
+                damage: 2.0,
+                damage_type: DamageType::Explosive,
+                projectile: Projectile::Shell,
+                bullet_speed: 250.0,
+                splash: 40.0,
+                mines: None,
+                synergies: &[RADAR_RANGE],
+                ..basic
+            },
+            TowerKind::Frost => TowerStats {
+                name: "Frost",
+                blurb: "Slows enemies",
+                texture: "sprites/FrostTower.png",
+                preview_texture: "sprites/UnbuiltFrostTower.png",
+                tint: MEDIUM_BLUE,
+                start_cost: 7,
+                fire_time: 0.8,
+                range: 150.0,
+                damage: 0.5,
+                damage_type: DamageType::Frost,
+                projectile: Projectile::Straight,
+                slow: 0.5,
+                mines: None,
+                synergies: &[RADAR_RANGE],
+                ..basic
+            },
+            TowerKind::Magnet => TowerStats {
+                name: "Magnet",
+                blurb: "Pulls in gold",
+                texture: "sprites/MagnetTower.png",
+                preview_texture: "sprites/UnbuiltMagnetTower.png",
+                tint: MAGENTA,
+                start_cost: 6,
+                cost_growth: 0.5,
+                range: 120.0,
+                damage: 0.0,
+                magnet: true,
+                mines: None,
+                synergies: &[RADAR_RANGE],
+                ..basic
+            },
+            TowerKind::Miner => TowerStats {
+                name: "Miner",
+                blurb: "Makes gold fast",
+                texture: "sprites/MinerTower.png",
+                preview_texture: "sprites/UnbuiltMinerTower.png",
+                tint: GOLD,
+                start_cost: 6,
+                cost_growth: 1.5,
+                damage: 0.0,
+                mines: Some(0.5),
+                synergies: &[SHARED_MINING, RICH_GROUND],
+                ..basic
+            },
+            TowerKind::Radar => TowerStats {
+                name: "Radar",
+                blurb: "Neighbours see further",
+                texture: "sprites/RadarTower.png",
+                preview_texture: "sprites/UnbuiltRadarTower.png",
+                tint: LIME_GREEN,
+                start_cost: 8,
+                damage: 0.0,
+                mines: None,
+                synergies: &[],
+                ..basic
+            },
+        }
+    }
+}
+
+// what a tower shoots at first, only ever inside its range
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TargetMode {
+    Nearest,
+    // closest to getting away
+    First,
+    // most health left
+    Strongest,
+    // whoever has the most gold
+    Carrying,
+    // closest to a pile with gold in it
+    NearPile,
+}
+
+impl TargetMode {
+    fn next(self) -> Self {
+        match self {
+            TargetMode::Nearest => TargetMode::First,
+            TargetMode::First => TargetMode::Strongest,
+            TargetMode::Strongest => TargetMode::Carrying,
+            TargetMode::Carrying => TargetMode::NearPile,
+            TargetMode::NearPile => TargetMode::Nearest,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Tower {
+    pub kind: TowerKind,
+    pub target_mode: TargetMode,
     pub coords: HexCoords,
+    // starts at 1
+    pub level: u32,
     pub refund: u32,
     shoot_timer: Timer,
     can_shoot: bool,
     range: f32,
+    damage: f32,
+    // from neighbouring hexes, kept up to date by the synergy plugin
+    pub bonus: TowerBonus,
 }
 
 impl Tower {
-    pub fn new(coords: HexCoords, refund: u32) -> Self {
+    pub fn new(kind: TowerKind, coords: HexCoords, refund: u32) -> Self {
+        let stats = kind.stats();
         Tower {
+            kind,
+            target_mode: TargetMode::Nearest,
             coords,
+            level: 1,
             refund,
-            shoot_timer: Timer::from_seconds(1.0, true),
+            shoot_timer: Timer::from_seconds(stats.fire_time, true),
             can_shoot: true,
-            range: 200.0,
+            range: stats.range,
+            damage: stats.damage,
+            bonus: TowerBonus::default(),
         }
     }
+
+    pub fn range(&self) -> f32 {
+        self.range * (1.0 + self.bonus.range)
+    }
+
+    // None for towers that don't mine
+    pub fn spawn_time(&self, difficulty: &Difficulty) -> Option<f32> {
+        let mines = self.kind.stats().mines?;
+        let level = LEVEL_FIRE_TIME.powi(self.level as i32 - 1);
+        Some(difficulty.gold_spawn_time * mines * level / (1.0 + self.bonus.mine_rate))
+    }
+
+    fn level_up(&mut self, invested: u32) {
+        self.level += 1;
+        self.range *= LEVEL_RANGE;
+        self.damage *= LEVEL_DAMAGE;
+        let fire_time = self.shoot_timer.duration().as_secs_f32() * LEVEL_FIRE_TIME;
+        self.shoot_timer
+            .set_duration(Duration::from_secs_f32(fire_time));
+        self.refund += (invested as f32 * REFUND_RATE) as u32;
+    }
 }
 
+// a built tower waiting on gold to level up
+// the gold goes in a GoldPile on the tower, same as a preview
+#[derive(Component)]
+pub struct UpgradeOrder {
+    cost: u32,
+}
+
+// the tower's own sprite, not its health bar
+#[derive(Component)]
+struct TowerSprite;
+
 #[derive(Component)]
-pub struct TowerPreview {}
+pub struct TowerPreview {
+    pub kind: TowerKind,
+}
 
 #[derive(Bundle)]
 pub struct PreviewTowerBundle {
@@ -68,8 +363,8 @@ pub struct PreviewTowerBundle {
 }
 
 struct PlaceTowerPreviewEvent {
-    position: Vec3,
     coords: HexCoords,
+    kind: TowerKind,
 }
 
 // successfully build
@@ -77,13 +372,60 @@ pub struct TowerBuiltEvent {
     pub coords: HexCoords,
 }
 
+// each kind gets more expensive the more of it there are
+#[derive(Default)]
 struct TowerSpawnCost {
-    cost: u32,
+    placed: [u32; TowerKind::ALL.len()],
+    // the difficulty's cost growth, fixed when the run starts
+    // so changing it later doesn't reprice anything
+    growth: u32,
+}
+
+impl TowerSpawnCost {
+    fn cost(&self, kind: TowerKind) -> u32 {
+        let stats = kind.stats();
+        let growth = self.growth as f32 * stats.cost_growth;
+        stats.start_cost + (self.placed[kind as usize] as f32 * growth).round() as u32
+    }
+
+    // grows with the level the same way building more of a kind does
+    fn upgrade_cost(&self, tower: &Tower) -> u32 {
+        let stats = tower.kind.stats();
+        let growth = self.growth as f32 * stats.cost_growth;
+        (stats.start_cost + (tower.level as f32 * growth).round() as u32) * tower.level
+    }
 }
 
-struct TowerCount {
-    count: u32,
-    boss_spawned: bool,
+// picking what to build on an empty hex
+#[derive(Component)]
+struct BuildMenu {
+    coords: HexCoords,
+}
+
+#[derive(Component)]
+struct BuildButton(TowerKind);
+
+// a preview waiting on a second X before it's cancelled
+#[derive(Default)]
+struct CancelConfirm {
+    pending: Option<(HexCoords, Timer)>,
+}
+
+// damage already flying at each target
+// so towers don't all waste shots on something that's about to die
+#[derive(Default)]
+struct IncomingDamage(HashMap<Entity, f32>);
+
+impl IncomingDamage {
+    // only counts once per bullet
+    fn landed(&mut self, bullet: &mut Bullet) {
+        if let Some(target) = bullet.target {
+            if let Some(dmg) = self.0.get_mut(&target) {
+                *dmg -= bullet.expected;
+            }
+        }
+        bullet.expected = 0.0;
+    }
 }
 
 // fn rotate_sprite(
@@ -104,20 +446,131 @@ struct TowerCount {
 //     }
 // }
 
+// clicking an empty hex opens the build menu for it
 fn tower_mouse_input(
-    mut ev_place_preview: EventWriter<PlaceTowerPreviewEvent>,
-    q_selection: Query<(&Transform, &Hex), With<Selection>>,
+    mut commands: Commands,
+    q_selection: Query<
+        &Hex,
+        (
+            With<Selection>,
+            Without<Tower>,
+            Without<TowerPreview>,
+            Without<GoldPile>,
+        ),
+    >,
+    q_menu: Query<Entity, With<BuildMenu>>,
+    q_buttons: Query<&Interaction, With<Button>>,
     input: Res<Input<MouseButton>>,
     accept: Res<AcceptInput>,
+    windows: Res<Windows>,
+    asset_server: Res<AssetServer>,
+    cost: Res<TowerSpawnCost>,
+) {
+    if !accept.0 {
+        return;
+    }
+    // clicks on the menu itself are for the buttons
+    if q_buttons.iter().any(|i| *i != Interaction::None) {
+        return;
+    }
+    if !input.just_pressed(MouseButton::Left) && !input.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    for ent in q_menu.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    if !input.just_pressed(MouseButton::Left) {
+        // right click just closes it
+        return;
+    }
+
+    let cursor = match windows.get_primary().and_then(|win| win.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    for hex in q_selection.iter() {
+        spawn_build_menu(&mut commands, &asset_server, hex.coords, cursor, &cost);
+    }
+}
+
+fn spawn_build_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    coords: HexCoords,
+    cursor: Vec2,
+    cost: &TowerSpawnCost,
 ) {
-    if accept.0 {
-        if input.just_pressed(MouseButton::Left) {
-            for (trans, hex) in q_selection.iter() {
-                ev_place_preview.send(PlaceTowerPreviewEvent {
-                    position: trans.translation,
-                    coords: hex.coords,
+    let height = (BUILD_BUTTON_HEIGHT + 4.0) * TowerKind::ALL.len() as f32;
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                // ui counts up from the bottom, hang it down from the cursor
+                position: UiRect {
+                    left: Val::Px(cursor.x + 12.0),
+                    bottom: Val::Px((cursor.y - height).max(0.0)),
+                    ..default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(BuildMenu { coords })
+        .with_children(|menu| {
+            for kind in TowerKind::ALL {
+                let stats = kind.stats();
+                let text = format!("{} {}", stats.name, cost.cost(kind));
+                menu.spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(BUILD_BUTTON_WIDTH), Val::Px(BUILD_BUTTON_HEIGHT)),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::rgb(0.15, 0.15, 0.15).into(),
+                    ..default()
+                })
+                .insert(BuildButton(kind))
+                .insert(KeepMenu)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            text.clone(),
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 20.0,
+                                color: stats.tint,
+                            },
+                        ))
+                        .insert(ButtonInfo {
+                            base_text: text,
+                            hovered_text: stats.blurb.to_string(),
+                        });
                 });
             }
+        });
+}
+
+fn build_menu_buttons(
+    mut commands: Commands,
+    q_interaction: Query<(&Interaction, &BuildButton), Changed<Interaction>>,
+    q_menu: Query<(Entity, &BuildMenu)>,
+    mut ev_place_preview: EventWriter<PlaceTowerPreviewEvent>,
+) {
+    for (interaction, button) in q_interaction.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        for (ent, menu) in q_menu.iter() {
+            ev_place_preview.send(PlaceTowerPreviewEvent {
+                coords: menu.coords,
+                kind: button.0,
+            });
+            commands.entity(ent).despawn_recursive();
         }
     }
 }
@@ -127,7 +580,10 @@ fn tower_mouse_input(
 fn spawn_tower_preview(
     mut commands: Commands,
     mut ev_place_preview: EventReader<PlaceTowerPreviewEvent>,
-    q_empty_hexes: Query<(Entity, &Hex), Or<(Without<Tower>, Without<GoldPile>)>>,
+    q_empty_hexes: Query<
+        (Entity, &Hex),
+        (Without<Tower>, Without<TowerPreview>, Without<GoldPile>),
+    >,
     asset_server: Res<AssetServer>,
     mut cost: ResMut<TowerSpawnCost>,
 ) {
@@ -138,35 +594,41 @@ fn spawn_tower_preview(
                 commands
                     .entity(ent)
                     .insert_bundle(PreviewTowerBundle {
-                        preview: TowerPreview {},
-                        pile: GoldPile::new(cost.cost),
+                        preview: TowerPreview { kind: ev.kind },
+                        pile: GoldPile::new(cost.cost(ev.kind)),
                     })
                     .with_children(|parent| {
-                        parent.spawn_bundle(SpriteBundle {
-                            texture: asset_server.load("sprites/UnbuiltTower.png"),
-                            // sprite: Sprite {
-                            //     color: LIGHT_BLUE,
-                            //     custom_size: Some(Vec2::new(20.0, 20.0)),
-                            //     ..default()
-                            // },
-                            transform: Transform {
-                                // spawn on top of the underlying hex
-                                translation: Vec3 {
-                                    x: 0.0,
-                                    y: 0.0,
-                                    z: 0.2,
+                        parent
+                            .spawn_bundle(SpriteBundle {
+                                texture: asset_server.load(ev.kind.stats().preview_texture),
+                                sprite: Sprite {
+                                    color: ev.kind.stats().tint,
+                                    ..default()
+                                },
+                                // sprite: Sprite {
+                                //     color: LIGHT_BLUE,
+                                //     custom_size: Some(Vec2::new(20.0, 20.0)),
+                                //     ..default()
+                                // },
+                                transform: Transform {
+                                    // spawn on top of the underlying hex
+                                    translation: Vec3 {
+                                        x: 0.0,
+                                        y: 0.0,
+                                        z: 0.2,
+                                    },
+                                    // undo the hex's rotation
+                                    rotation: Quat::from_rotation_z(-30.0 * DEG_TO_RAD),
+                                    ..default()
                                 },
-                                // undo the hex's rotation
-                                rotation: Quat::from_rotation_z(-30.0 * DEG_TO_RAD),
                                 ..default()
-                            },
-                            ..default()
-                        });
+                            })
+                            .insert(TowerSprite);
                     });
 
                 // it is now a Hex, TowerPreview, GoldPile,
                 // with a sprite child
-                cost.cost += TOWER_COST_GROWTH;
+                cost.placed[ev.kind as usize] += 1;
             }
         }
     }
@@ -175,44 +637,35 @@ fn spawn_tower_preview(
 fn preview_paid_for(
     mut commands: Commands,
     mut ev_pile_cap: EventReader<PileCapEvent>,
-    q_preview_towers: Query<(Entity, &Children, &Hex, &GoldPile), With<TowerPreview>>,
-    mut q_child: Query<&mut Handle<Image>>,
+    q_preview_towers: Query<(Entity, &Children, &Hex, &GoldPile, &TowerPreview)>,
+    // not the health bar
+    mut q_child: Query<&mut Handle<Image>, With<TowerSprite>>,
     asset_server: Res<AssetServer>,
-    mut tower_count: ResMut<TowerCount>,
-    mut ev_boss: EventWriter<BossSpawnEvent>,
+    difficulty: Res<Difficulty>,
 ) {
     for ev in ev_pile_cap.iter() {
-        for (ent, children, hex, pile) in q_preview_towers.iter() {
+        for (ent, children, hex, pile, preview) in q_preview_towers.iter() {
             if ev.coords.is_same(hex.coords) {
                 //println!("Upgrade {:?}", hex.coords);
 
-                // change the color of the preview to a tower color
+                // swap the preview sprite for the built one
                 for &child in children.iter() {
-                    let sprite = q_child.get_mut(child);
-                    match sprite {
-                        Ok(mut s) => {
-                            //s.color = DARK_BLUE;
-                            *s = asset_server.load("sprites/Tower.png");
-                        }
-                        Err(e) => {
-                            error!("Error getting child sprite: {e}");
-                        }
+                    if let Ok(mut s) = q_child.get_mut(child) {
+                        //s.color = DARK_BLUE;
+                        *s = asset_server.load(preview.kind.stats().texture);
                     }
                 }
 
+                let refund = (pile.gold_cap as f32 * REFUND_RATE) as u32;
                 commands
                     .entity(ent)
                     //.remove_children(children)
                     .remove_bundle::<PreviewTowerBundle>()
-                    .insert(Tower::new(ev.coords, (pile.gold_cap as f32 * 0.8) as u32))
-                    .insert(GoldSpawner::new());
-
-                if !tower_count.boss_spawned {
-                    tower_count.count += 1;
-                    if tower_count.count == TOWERS_TO_SPAWN_BOSS {
-                        tower_count.boss_spawned = true;
-                        ev_boss.send(BossSpawnEvent);
-                    }
+                    .insert(Tower::new(preview.kind, ev.coords, refund));
+                if let Some(mines) = preview.kind.stats().mines {
+                    commands
+                        .entity(ent)
+                        .insert(GoldSpawner::new(difficulty.gold_spawn_time * mines));
                 }
 
                 break;
@@ -221,6 +674,77 @@ fn preview_paid_for(
     }
 }
 
+// u on a selected tower
+fn order_upgrade(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    q_selection: Query<(Entity, &Tower), (With<Selection>, Without<UpgradeOrder>)>,
+    cost: Res<TowerSpawnCost>,
+    accept: Res<AcceptInput>,
+) {
+    if !accept.0 || !input.just_pressed(KeyCode::U) {
+        return;
+    }
+    for (ent, tower) in q_selection.iter() {
+        if tower.level >= MAX_LEVEL {
+            continue;
+        }
+        let cost = cost.upgrade_cost(tower);
+        commands
+            .entity(ent)
+            .insert(UpgradeOrder { cost })
+            .insert(GoldPile::new(cost));
+    }
+}
+
+fn upgrade_paid_for(
+    mut commands: Commands,
+    mut ev_pile_cap: EventReader<PileCapEvent>,
+    mut q_orders: Query<(Entity, &Children, &Hex, &mut Tower, &UpgradeOrder)>,
+    mut q_sprite: Query<(&mut Transform, &mut Sprite), With<TowerSprite>>,
+    difficulty: Res<Difficulty>,
+) {
+    for ev in ev_pile_cap.iter() {
+        for (ent, children, hex, mut tower, order) in q_orders.iter_mut() {
+            if !ev.coords.is_same(hex.coords) {
+                continue;
+            }
+            tower.level_up(order.cost);
+
+            // bigger and lighter every level
+            for &child in children.iter() {
+                if let Ok((mut trans, mut sprite)) = q_sprite.get_mut(child) {
+                    trans.scale = Vec3::splat(1.0 + (tower.level - 1) as f32 * LEVEL_SCALE);
+                    sprite.color = level_tint(tower.kind.stats().tint, tower.level);
+                }
+            }
+
+            // the health bar goes with the pile
+            commands
+                .entity(ent)
+                .remove::<UpgradeOrder>()
+                .remove::<GoldPile>();
+            // miners speed up too
+            if let Some(spawn_time) = tower.spawn_time(&difficulty) {
+                commands.entity(ent).insert(GoldSpawner::new(spawn_time));
+            }
+            break;
+        }
+    }
+}
+
+// fades towards YELLOW_WHITE
+fn level_tint(tint: Color, level: u32) -> Color {
+    let t = ((level - 1) as f32 * LEVEL_TINT).min(1.0);
+    let from = tint.as_rgba_f32();
+    let to = YELLOW_WHITE.as_rgba_f32();
+    Color::rgb(
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+    )
+}
+
 fn remove_tower(
     mut commands: Commands,
     mut ev_remove: EventReader<PileRemoveEvent>,
@@ -233,13 +757,25 @@ fn remove_tower(
         Option<&GoldPile>,
         Option<&TowerPreview>,
         Option<&Tower>,
+        Option<&UpgradeOrder>,
     )>,
-    mut counter: ResMut<TowerCount>,
     mut cost: ResMut<TowerSpawnCost>,
+    mut confirm: ResMut<CancelConfirm>,
+    time: Res<Time>,
     //mut q_child: Query<&mut Sprite>,
 ) {
+    let expired = confirm
+        .pending
+        .as_mut()
+        .map_or(false, |(_, timer)| timer.tick(time.delta()).finished());
+    if expired {
+        confirm.pending = None;
+    }
+
     for ev in ev_remove.iter() {
-        for (ent, children, trans, hex, _opt_pile, opt_preview, opt_tower) in q_towers.iter() {
+        for (ent, children, trans, hex, opt_pile, opt_preview, opt_tower, opt_order) in
+            q_towers.iter()
+        {
             if ev.coords.is_same(hex.coords) {
                 let mut opt_count = 0;
                 if let Some(_) = opt_preview {
@@ -253,14 +789,42 @@ fn remove_tower(
                     break;
                 }
 
-                let mut pile_count = 0;
+                // gold already brought to a preview or an upgrade
+                let delivered = opt_pile.map_or(0, |pile| pile.count);
 
-                // if let Some(pile) = opt_pile {
-                //     pile_count = pile.count;
+                // x on an upgrading tower only calls off the upgrade
+                if opt_order.is_some() {
+                    for _ in 0..delivered {
+                        ev_spawn_gold.send(SpawnGoldEvent {
+                            position: trans.translation,
+                        });
+                    }
+                    // the health bar goes with the pile
+                    commands
+                        .entity(ent)
+                        .remove::<UpgradeOrder>()
+                        .remove::<GoldPile>();
+                    break;
+                }
 
-                // } else
+                // don't throw away a nearly built tower by accident
+                if let (Some(_), Some(pile)) = (opt_preview, opt_pile) {
+                    let confirmed = match &confirm.pending {
+                        Some((coords, _)) => coords.is_same(hex.coords),
+                        None => false,
+                    };
+                    if pile.count * 2 >= pile.gold_cap && pile.count > 0 && !confirmed {
+                        // show_tower_info asks for the second press
+                        confirm.pending =
+                            Some((hex.coords, Timer::from_seconds(CANCEL_CONFIRM_TIME, false)));
+                        break;
+                    }
+                }
+                confirm.pending = None;
+
+                let mut pile_count = delivered;
                 if let Some(tower) = opt_tower {
-                    pile_count = tower.refund;
+                    pile_count += tower.refund;
                 }
                 //println!("Pile count: {:?}", pile_count);
 
@@ -276,9 +840,16 @@ fn remove_tower(
                     commands.entity(child).despawn_recursive();
                 }
 
+                // towers and previews get their piles cleaned up here, not in remove_pile
+                commands.entity(ent).remove::<GoldPile>();
+
                 match opt_preview {
-                    Some(_) => {
+                    Some(preview) => {
                         commands.entity(ent).remove::<TowerPreview>();
+
+                        // it was counted when it was placed
+                        let placed = &mut cost.placed[preview.kind as usize];
+                        *placed = placed.saturating_sub(1);
                     }
                     None => {
                         //println!("No Preview");
@@ -286,22 +857,14 @@ fn remove_tower(
                 }
 
                 match opt_tower {
-                    Some(_) => {
+                    Some(tower) => {
                         commands.entity(ent).remove::<GoldSpawner>();
                         commands.entity(ent).remove::<Tower>();
+                        commands.entity(ent).remove::<UpgradeOrder>();
 
-                        if !counter.boss_spawned {
-                            // probably can't underflow
-                            // can only destroy a tower if it exists
-                            // but to be safe
-                            if counter.count > 1 {
-                                counter.count -= 1;
-                            }
-                        }
-                        // likewise shouldn't need this check either
-                        if cost.cost > TOWER_COST_GROWTH {
-                            cost.cost -= TOWER_COST_GROWTH;
-                        }
+                        // shouldn't be able to underflow
+                        let placed = &mut cost.placed[tower.kind as usize];
+                        *placed = placed.saturating_sub(1);
                     }
                     None => {
                         //println!("No Tower");
@@ -312,36 +875,215 @@ fn remove_tower(
     }
 }
 
+// back to an empty board
+fn reset_towers(
+    mut commands: Commands,
+    mut ev_restart: EventReader<RestartEvent>,
+    q_towers: Query<(Entity, Option<&Children>), Or<(With<Tower>, With<TowerPreview>)>>,
+    q_bullets: Query<Entity, Or<(With<Bullet>, With<BuildMenu>)>>,
+    mut cost: ResMut<TowerSpawnCost>,
+    mut incoming: ResMut<IncomingDamage>,
+    mut confirm: ResMut<CancelConfirm>,
+) {
+    for _ev in ev_restart.iter() {
+        for (ent, children) in q_towers.iter() {
+            if let Some(children) = children {
+                for &child in children {
+                    commands.entity(child).despawn_recursive();
+                }
+            }
+            commands
+                .entity(ent)
+                .remove::<Tower>()
+                .remove::<GoldSpawner>()
+                .remove::<UpgradeOrder>()
+                .remove_bundle::<PreviewTowerBundle>()
+                .remove::<GoldPile>();
+        }
+        for ent in q_bullets.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+        // growth is set again when the next run starts
+        cost.placed = Default::default();
+        incoming.0.clear();
+        confirm.pending = None;
+    }
+}
+
+fn fix_cost_growth(
+    mut ev_start: EventReader<StartSpawningEnemiesEvent>,
+    mut cost: ResMut<TowerSpawnCost>,
+    difficulty: Res<Difficulty>,
+) {
+    for _ev in ev_start.iter() {
+        cost.growth = difficulty.tower_cost_growth;
+    }
+}
+
+// tab on a selected tower
+fn cycle_target_mode(
+    input: Res<Input<KeyCode>>,
+    mut q_selection: Query<&mut Tower, With<Selection>>,
+) {
+    if !input.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    for mut tower in q_selection.iter_mut() {
+        tower.target_mode = tower.target_mode.next();
+    }
+}
+
+// what the hovered tower is up to
+#[derive(Component)]
+struct TowerLabel;
+
+fn spawn_tower_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(TowerLabel);
+}
+
+fn show_tower_info(
+    q_selection: Query<(&Tower, Option<&GoldPile>), With<Selection>>,
+    q_previews: Query<(&Hex, &TowerPreview, &GoldPile), With<Selection>>,
+    mut q_label: Query<&mut Text, With<TowerLabel>>,
+    cost: Res<TowerSpawnCost>,
+    confirm: Res<CancelConfirm>,
+) {
+    let mut lines = Vec::new();
+    for (tower, order) in q_selection.iter() {
+        lines.push(format!("{} level {}", tower.kind.stats().name, tower.level));
+        // a pile on a built tower is an upgrade order
+        if let Some(pile) = order {
+            lines.push(format!(
+                "Upgrading: {}/{} gold (X to cancel)",
+                pile.count, pile.gold_cap
+            ));
+        } else if tower.level < MAX_LEVEL {
+            lines.push(format!("Upgrade for {} gold (U)", cost.upgrade_cost(tower)));
+        }
+        if tower.damage > 0.0 {
+            lines.push(format!("Targeting {:?} (Tab)", tower.target_mode));
+        }
+        // from the synergy plugin
+        lines.extend(tower.bonus.notes.iter().cloned());
+    }
+    for (hex, preview, pile) in q_previews.iter() {
+        lines.push(format!(
+            "Building {}: {}/{} gold",
+            preview.kind.stats().name,
+            pile.count,
+            pile.gold_cap
+        ));
+        // goes away again when the confirm times out
+        if let Some((coords, _)) = &confirm.pending {
+            if coords.is_same(hex.coords) {
+                lines.push(format!("Press X again to cancel, {} gold back", pile.count));
+            }
+        }
+    }
+    let value = lines.join("\n");
+    for mut text in q_label.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
 fn tower_shoot(
     mut q_towers: Query<(&Transform, &mut Tower)>,
-    q_enemies: Query<(&Transform, &Enemy)>,
+    q_targets: Query<
+        (Entity, &Transform, &Health, Option<&Enemy>, Option<&Boid>),
+        (Or<(With<Enemy>, With<Boss>)>, Without<Dead>),
+    >,
+    q_piles: Query<(&Transform, &GoldPile), (With<Hex>, Without<Tower>)>,
+    mut incoming: ResMut<IncomingDamage>,
     mut ev_shoot: EventWriter<SpawnBulletEvent>,
     time: Res<Time>,
 ) {
+    // forget anything that's gone or has nothing left coming
+    incoming
+        .0
+        .retain(|ent, dmg| *dmg > 0.01 && q_targets.get(*ent).is_ok());
+
+    let piles: Vec<Vec2> = q_piles
+        .iter()
+        .filter(|(_, pile)| pile.count > 0)
+        .map(|(trans, _)| trans.translation.truncate())
+        .collect();
+
     for (t_trans, mut t) in q_towers.iter_mut() {
+        if t.damage <= 0.0 {
+            // magnets and miners
+            continue;
+        }
         if t.can_shoot {
             // can shoot
             // find a target
-            let direction = q_enemies
+            let t_pos = t_trans.translation.truncate();
+            let stats = t.kind.stats();
+            let target = q_targets
                 .iter()
-                .min_by_key(|target_transform| {
-                    FloatOrd(Vec3::distance(
-                        target_transform.0.translation,
-                        t_trans.translation,
+                .filter(|(_, trans, _, _, _)| {
+                    trans.translation.truncate().distance(t_pos) < t.range()
+                })
+                .filter(|(ent, _, health, _, _)| {
+                    // already as good as dead
+                    incoming.0.get(ent).copied().unwrap_or(0.0) < health.current
+                })
+                .max_by_key(|(_, trans, health, enemy, _)| {
+                    FloatOrd(target_score(
+                        t.target_mode,
+                        t_pos,
+                        trans.translation.truncate(),
+                        health,
+                        *enemy,
+                        &piles,
                     ))
                 })
-                .map(|closest_target| closest_target.0.translation - t_trans.translation);
+                .map(|(ent, trans, health, _, boid)| {
+                    let expected = health.damage_from(t.damage, stats.damage_type);
+                    let pos = trans.translation.truncate();
+                    // homing shots sort themselves out
+                    let aim = match (stats.projectile, boid) {
+                        (Projectile::Homing, _) | (_, None) => pos,
+                        (_, Some(boid)) => {
+                            lead_target(t_pos, pos, boid.heading(), stats.bullet_speed)
+                        }
+                    };
+                    (ent, aim, expected)
+                });
 
-            if let Some(direction) = direction {
+            if let Some((target, aim, expected)) = target {
                 //println!("Shoot a bullet");
-                // only shoot if within range
-                if direction.length_squared() < (t.range * t.range) {
-                    ev_shoot.send(SpawnBulletEvent {
-                        pos: t_trans.translation.truncate(),
-                        dir: direction.truncate(),
-                    });
-                    t.can_shoot = false;
-                }
+                *incoming.0.entry(target).or_insert(0.0) += expected;
+                ev_shoot.send(SpawnBulletEvent {
+                    pos: t_pos,
+                    aim,
+                    damage: t.damage,
+                    kind: t.kind,
+                    target,
+                    expected,
+                });
+                t.can_shoot = false;
             }
         } else {
             // tick between shots when you can't shoot
@@ -352,24 +1094,164 @@ fn tower_shoot(
     }
 }
 
+// higher is shot first
+fn target_score(
+    mode: TargetMode,
+    tower: Vec2,
+    pos: Vec2,
+    health: &Health,
+    enemy: Option<&Enemy>,
+    piles: &[Vec2],
+) -> f32 {
+    let dist = pos.distance(tower);
+    let carrying = enemy.map_or(0, |enemy| enemy.carrying());
+    match mode {
+        TargetMode::Nearest => -dist,
+        TargetMode::First => {
+            // heading in they get closer to the middle, heading out further away
+            if carrying > 0 {
+                10000.0 + pos.length()
+            } else {
+                -pos.length()
+            }
+        }
+        TargetMode::Strongest => health.current,
+        // nearest breaks ties
+        TargetMode::Carrying => carrying as f32 * 10000.0 - dist,
+        TargetMode::NearPile => -piles
+            .iter()
+            .map(|pile| pile.distance(pos))
+            .fold(f32::MAX, f32::min),
+    }
+}
+
+// pulls loose gold in range towards the tower
+// where it's easy to pick back up
+fn magnet_pull(
+    q_towers: Query<(&Transform, &Tower)>,
+    mut q_gold: Query<&mut Transform, (With<Gold>, Without<Tower>)>,
+    index: Res<SpatialIndex>,
+    time: Res<Time>,
+) {
+    for (t_trans, tower) in q_towers.iter() {
+        if !tower.kind.stats().magnet {
+            continue;
+        }
+        let center = t_trans.translation.truncate();
+        for (gold_ent, pos) in index.loose_gold.query(center, tower.range()) {
+            let d = pos.distance(center);
+            if d > tower.range() || d < MAGNET_HOLD {
+                continue;
+            }
+            if let Ok(mut trans) = q_gold.get_mut(gold_ent) {
+                let dir = (center - pos).normalize_or_zero();
+                trans.translation += dir.extend(0.0) * MAGNET_SPEED * time.delta_seconds();
+            }
+        }
+    }
+}
+
+// how a shot gets to what it's fired at
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Projectile {
+    Straight,
+    // turns to follow its target
+    Homing,
+    // carries on through a few enemies
+    Piercing,
+    // flies over everything and bursts where it was aimed
+    Shell,
+}
+
 #[derive(Component)]
 pub struct Bullet {
     dir: Vec2,
+    speed: f32,
     timer: Timer,
+    damage: f32,
+    damage_type: DamageType,
+    projectile: Projectile,
+    splash: f32,
+    slow: f32,
+    // what it was fired at and how much it should take off
+    target: Option<Entity>,
+    expected: f32,
+    // shells burst after going this far
+    land_dist: f32,
+    travelled: f32,
+    // piercing shots don't hit the same thing twice
+    hits: Vec<Entity>,
 }
 
 impl Bullet {
-    pub fn new(dir: Vec2) -> Self {
+    pub fn new(dir: Vec2, damage: f32, stats: &TowerStats) -> Self {
         Bullet {
             dir,
-            timer: Timer::from_seconds(1.0, false),
+            speed: stats.bullet_speed,
+            // a bit longer than it takes to cross the range
+            timer: Timer::from_seconds(stats.range / stats.bullet_speed + 0.5, false),
+            damage,
+            damage_type: stats.damage_type,
+            projectile: stats.projectile,
+            splash: stats.splash,
+            slow: stats.slow,
+            target: None,
+            expected: 0.0,
+            land_dist: f32::MAX,
+            travelled: 0.0,
+            hits: Vec::new(),
         }
     }
 }
 
 struct SpawnBulletEvent {
     pos: Vec2,
-    dir: Vec2,
+    // where it's aimed, ahead of moving targets
+    aim: Vec2,
+    damage: f32,
+    kind: TowerKind,
+    target: Entity,
+    expected: f32,
+}
+
+// where to shoot so a bullet at speed meets something moving at vel
+// falls back to where it is now if it can't be caught
+fn lead_target(from: Vec2, pos: Vec2, vel: Vec2, speed: f32) -> Vec2 {
+    let d = pos - from;
+    // |d + vel * t| = speed * t
+    let a = vel.length_squared() - speed * speed;
+    let b = 2.0 * d.dot(vel);
+    let c = d.length_squared();
+    let t = if a.abs() < 0.001 {
+        if b.abs() < 0.001 {
+            return pos;
+        }
+        -c / b
+    } else {
+        let disc = b * b - 4.0 * a * c;
+        if disc < 0.0 {
+            return pos;
+        }
+        let root = disc.sqrt();
+        let t1 = (-b - root) / (2.0 * a);
+        let t2 = (-b + root) / (2.0 * a);
+        match (t1 > 0.0, t2 > 0.0) {
+            (true, true) => t1.min(t2),
+            (true, false) => t1,
+            (false, true) => t2,
+            (false, false) => return pos,
+        }
+    };
+    if t > 0.0 {
+        pos + vel * t
+    } else {
+        pos
+    }
+}
+
+// Missile.png points left
+fn bullet_rotation(dir: Vec2) -> Quat {
+    Quat::from_rotation_z(dir.y.atan2(dir.x) - PI)
 }
 
 fn spawn_bullet(
@@ -378,21 +1260,19 @@ fn spawn_bullet(
     asset_server: Res<AssetServer>,
 ) {
     for ev in ev_spawn_bullet.iter() {
-        //println!("Spawn a bullet. pos: {:?}, dir: {:?}", ev.pos, ev.dir);
+        //println!("Spawn a bullet. pos: {:?}, aim: {:?}", ev.pos, ev.aim);
+        let stats = ev.kind.stats();
+        let dir = (ev.aim - ev.pos).normalize_or_zero();
+        let land_dist = if stats.projectile == Projectile::Shell {
+            ev.aim.distance(ev.pos)
+        } else {
+            f32::MAX
+        };
         commands
             .spawn_bundle(SpriteBundle {
                 texture: asset_server.load("sprites/Missile.png"),
                 sprite: Sprite {
-                    // Flip the logo to the left
-                    flip_x: {
-                        if ev.dir.x > 0.0 {
-                            true
-                        } else {
-                            false
-                        }
-                    },
-                    // And don't flip it upside-down ( the default )
-                    flip_y: false,
+                    color: stats.tint,
                     ..default()
                 },
                 // sprite: Sprite {
@@ -406,59 +1286,212 @@ fn spawn_bullet(
                         y: ev.pos.y,
                         z: 0.5,
                     },
+                    rotation: bullet_rotation(dir),
                     ..default()
                 },
                 ..default()
             })
-            .insert(Bullet::new(ev.dir.normalize_or_zero()));
+            .insert(Bullet {
+                target: Some(ev.target),
+                expected: ev.expected,
+                land_dist,
+                ..Bullet::new(dir, ev.damage, &stats)
+            });
     }
 }
 
 fn tick_bullet(
     mut commands: Commands,
     mut q_bullet: Query<(Entity, &mut Bullet)>,
+    mut incoming: ResMut<IncomingDamage>,
     time: Res<Time>,
 ) {
     for (ent, mut b) in q_bullet.iter_mut() {
         if b.timer.tick(time.delta()).just_finished() {
+            // missed
+            incoming.landed(&mut b);
             commands.entity(ent).despawn_recursive();
         }
     }
 }
 
-fn move_bullet(mut q_bullet: Query<(&mut Transform, &Bullet)>, time: Res<Time>) {
-    for (mut trans, b) in q_bullet.iter_mut() {
-        trans.translation += b.dir.extend(0.0) * time.delta_seconds() * 400.0;
+fn move_bullet(
+    mut q_bullet: Query<(&mut Transform, &mut Bullet)>,
+    q_targets: Query<&Transform, (Or<(With<Enemy>, With<Boss>)>, Without<Bullet>)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (mut trans, mut b) in q_bullet.iter_mut() {
+        if b.projectile == Projectile::Homing {
+            // flies straight on if the target is gone
+            if let Some(t_trans) = b.target.and_then(|target| q_targets.get(target).ok()) {
+                let want = t_trans.translation.truncate() - trans.translation.truncate();
+                let current = b.dir.y.atan2(b.dir.x);
+                let mut turn = want.y.atan2(want.x) - current;
+                // the short way round
+                if turn > PI {
+                    turn -= TAU;
+                } else if turn < -PI {
+                    turn += TAU;
+                }
+                let angle = current + turn.clamp(-HOMING_TURN * dt, HOMING_TURN * dt);
+                b.dir = Vec2::new(angle.cos(), angle.sin());
+            }
+        }
+
+        let step = b.speed * dt;
+        trans.translation += b.dir.extend(0.0) * step;
+        trans.rotation = bullet_rotation(b.dir);
+        b.travelled += step;
     }
 }
 
 pub fn bullet_hit(
     mut commands: Commands,
-    q_bullet: Query<(Entity, &Transform), With<Bullet>>,
-    q_enemies: Query<(Entity, &Transform), (Without<Bullet>, Without<Dead>, With<Enemy>)>,
+    mut q_bullet: Query<(Entity, &Transform, &mut Bullet)>,
+    q_enemies: Query<(&Transform, &Enemy), (Without<Bullet>, Without<Dead>)>,
+    q_boss: Query<(Entity, &Transform, &Boss), (Without<Bullet>, Without<Dead>)>,
+    index: Res<SpatialIndex>,
+    mut incoming: ResMut<IncomingDamage>,
+    mut ev_damage: EventWriter<DamageEvent>,
 ) {
-    for (b_ent, b_trans) in q_bullet.iter() {
-        for (e_ent, e_trans) in q_enemies.iter() {
+    'bullets: for (b_ent, b_trans, mut bullet) in q_bullet.iter_mut() {
+        let b_pos = b_trans.translation.truncate();
+
+        // shells only go off where they were aimed
+        if bullet.projectile == Projectile::Shell {
+            if bullet.travelled >= bullet.land_dist {
+                for (boss_ent, boss_trans, boss) in q_boss.iter() {
+                    let d = boss_trans.translation.truncate().distance(b_pos) - boss.size * 0.5;
+                    if d < bullet.splash {
+                        ev_damage.send(DamageEvent {
+                            target: boss_ent,
+                            amount: splash_damage(&bullet, d.max(0.0)),
+                            kind: bullet.damage_type,
+                            knockback: Vec2::ZERO,
+                        });
+                    }
+                }
+                explode(
+                    &mut commands,
+                    &mut ev_damage,
+                    &bullet,
+                    b_pos,
+                    &q_enemies,
+                    &index,
+                );
+                incoming.landed(&mut bullet);
+                commands.entity(b_ent).despawn_recursive();
+            }
+            continue;
+        }
+
+        // only ever one of these so it doesn't need the index
+        for (boss_ent, boss_trans, boss) in q_boss.iter() {
+            if let Some(_) = collide(
+                b_trans.translation,
+                Vec2::new(6., 6.),
+                boss_trans.translation,
+                Vec2::new(boss.size, boss.size),
+            ) {
+                // too big to push around, slow down or go through
+                ev_damage.send(DamageEvent {
+                    target: boss_ent,
+                    amount: bullet.damage,
+                    kind: bullet.damage_type,
+                    knockback: Vec2::ZERO,
+                });
+                incoming.landed(&mut bullet);
+                commands.entity(b_ent).despawn_recursive();
+                continue 'bullets;
+            }
+        }
+
+        let nearby = index.enemies.query(b_pos, 25.0);
+        for (e_ent, _) in nearby {
+            if bullet.hits.contains(&e_ent) {
+                continue;
+            }
+            let (e_trans, enemy) = match q_enemies.get(e_ent) {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
             if let Some(_) = collide(
                 b_trans.translation,
                 Vec2::new(6., 6.),
                 e_trans.translation,
-                Vec2::new(15., 15.),
+                Vec2::new(enemy.size(), enemy.size()),
             ) {
                 //println!("Blam!");
-                // Todo drop gold
-                commands.entity(e_ent).insert(Dead);
+                hurt_enemy(
+                    &mut commands,
+                    &mut ev_damage,
+                    &bullet,
+                    e_ent,
+                    bullet.damage,
+                    bullet.dir * BULLET_KNOCKBACK,
+                );
+                bullet.hits.push(e_ent);
+                if bullet.target == Some(e_ent) {
+                    incoming.landed(&mut bullet);
+                }
 
-                commands.entity(b_ent).despawn_recursive();
+                if bullet.projectile != Projectile::Piercing || bullet.hits.len() > PIERCE_COUNT {
+                    incoming.landed(&mut bullet);
+                    commands.entity(b_ent).despawn_recursive();
+                    break;
+                }
+            }
+        }
+    }
+}
 
-                // println!("Grabbed a gold");
-                // enemy.has_gold = true;
-                // commands.entity(ent).remove::<Gold>();
+// less at the edge of the splash
+fn splash_damage(bullet: &Bullet, d: f32) -> f32 {
+    bullet.damage * (1.0 - (d / bullet.splash).min(1.0) * SPLASH_FALLOFF)
+}
 
-                // commands.entity(e_ent).add_child(ent);
-                // gold_trans.translation = Vec3::new(0.0, 0.0, 0.1);
-                break;
-            }
+// everything in the splash gets hit and pushed away from the middle
+fn explode(
+    commands: &mut Commands,
+    ev_damage: &mut EventWriter<DamageEvent>,
+    bullet: &Bullet,
+    center: Vec2,
+    q_enemies: &Query<(&Transform, &Enemy), (Without<Bullet>, Without<Dead>)>,
+    index: &SpatialIndex,
+) {
+    for (e_ent, e_pos) in index.enemies.query(center, bullet.splash) {
+        let d = e_pos.distance(center);
+        if d > bullet.splash || q_enemies.get(e_ent).is_err() {
+            continue;
         }
+        let away = (e_pos - center).normalize_or_zero();
+        hurt_enemy(
+            commands,
+            ev_damage,
+            bullet,
+            e_ent,
+            splash_damage(bullet, d),
+            away * BULLET_KNOCKBACK,
+        );
+    }
+}
+
+fn hurt_enemy(
+    commands: &mut Commands,
+    ev_damage: &mut EventWriter<DamageEvent>,
+    bullet: &Bullet,
+    target: Entity,
+    amount: f32,
+    knockback: Vec2,
+) {
+    ev_damage.send(DamageEvent {
+        target,
+        amount,
+        kind: bullet.damage_type,
+        knockback,
+    });
+    if bullet.slow < 1.0 {
+        commands.entity(target).insert(Chilled::new(bullet.slow));
     }
 }
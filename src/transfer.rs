@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+
+use crate::{
+    gold::{spawn_gold_bundle, Banked, Gold, GoldDecaySettings, GoldPile, Vault, Withdrawn},
+    hexes::{Hex, Selection},
+    palette::*,
+    tutorial::AcceptInput,
+    RestartEvent,
+};
+
+const TRANSFER_DEFAULT_AMOUNT: u32 = 10;
+const TRANSFER_STEP: u32 = 5;
+// one piece leaves the source this often
+const TRANSFER_RATE: f32 = 0.25;
+const COURIER_SPEED: f32 = 120.0;
+
+pub struct TransferPlugin;
+
+impl Plugin for TransferPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TransferDraft {
+            source: None,
+            amount: TRANSFER_DEFAULT_AMOUNT,
+        })
+        .add_system(transfer_input)
+        .add_system(show_transfer_amount.after(transfer_input))
+        .add_system(run_transfers)
+        .add_system(move_couriers)
+        .add_system(reset_transfers);
+    }
+}
+
+// moves gold from one pile to another a piece at a time
+#[derive(Component)]
+pub struct TransferOrder {
+    source: Entity,
+    dest: Entity,
+    remaining: u32,
+    timer: Timer,
+}
+
+// gold travelling for a transfer
+// still Gold so enemies can grab it on the way
+#[derive(Component)]
+pub struct Courier {
+    order: Entity,
+    pub dest: Entity,
+}
+
+#[derive(Component)]
+struct SourceMarker;
+
+// how much the draft will send, on the source marker
+#[derive(Component)]
+struct AmountText;
+
+struct TransferDraft {
+    source: Option<Entity>,
+    amount: u32,
+}
+
+// T on a pile picks the source
+// +/- changes the amount
+// T on another pile or preview sends it
+// shift+T on a pile cancels its orders
+fn transfer_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    accept: Res<AcceptInput>,
+    asset_server: Res<AssetServer>,
+    mut draft: ResMut<TransferDraft>,
+    q_selection: Query<(Entity, &Transform), (With<Selection>, With<GoldPile>, With<Hex>)>,
+    q_sources: Query<Entity, With<Vault>>,
+    q_orders: Query<(Entity, &TransferOrder)>,
+    q_couriers: Query<(Entity, &Courier)>,
+    q_marker: Query<Entity, With<SourceMarker>>,
+) {
+    if !accept.0 {
+        return;
+    }
+
+    if let Some(_) = draft.source {
+        if input.just_pressed(KeyCode::Equals) {
+            draft.amount += TRANSFER_STEP;
+        }
+        if input.just_pressed(KeyCode::Minus) {
+            draft.amount = draft.amount.saturating_sub(TRANSFER_STEP).max(1);
+        }
+    }
+
+    let mut clear_draft = input.just_pressed(KeyCode::Escape);
+
+    if input.just_pressed(KeyCode::T) {
+        for (ent, trans) in q_selection.iter() {
+            if input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+                for (order_ent, order) in q_orders.iter() {
+                    if order.source == ent || order.dest == ent {
+                        cancel_order(&mut commands, order_ent, &q_couriers);
+                    }
+                }
+                continue;
+            }
+
+            match draft.source {
+                None => {
+                    // can only take out of player piles
+                    if q_sources.contains(ent) {
+                        draft.source = Some(ent);
+                        commands
+                            .spawn_bundle(SpriteBundle {
+                                sprite: Sprite {
+                                    color: LIGHT_BLUE,
+                                    custom_size: Some(Vec2::new(26.0, 26.0)),
+                                    ..default()
+                                },
+                                transform: Transform::from_translation(
+                                    trans.translation.truncate().extend(0.28),
+                                ),
+                                ..default()
+                            })
+                            .insert(SourceMarker)
+                            .with_children(|parent| {
+                                parent
+                                    .spawn_bundle(Text2dBundle {
+                                        text: Text::from_section(
+                                            draft.amount.to_string(),
+                                            TextStyle {
+                                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                                font_size: 16.0,
+                                                color: Color::WHITE,
+                                            },
+                                        )
+                                        .with_alignment(TextAlignment::CENTER),
+                                        // above the marker
+                                        transform: Transform::from_xyz(0.0, 22.0, 0.1),
+                                        ..default()
+                                    })
+                                    .insert(AmountText);
+                            });
+                    }
+                }
+                Some(source) => {
+                    if source != ent {
+                        commands.spawn().insert(TransferOrder {
+                            source,
+                            dest: ent,
+                            remaining: draft.amount,
+                            timer: Timer::from_seconds(TRANSFER_RATE, true),
+                        });
+                    }
+                    // T on the source again just cancels
+                    clear_draft = true;
+                }
+            }
+        }
+    }
+
+    if clear_draft {
+        draft.source = None;
+        draft.amount = TRANSFER_DEFAULT_AMOUNT;
+        for ent in q_marker.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
+
+fn show_transfer_amount(draft: Res<TransferDraft>, mut q_text: Query<&mut Text, With<AmountText>>) {
+    if !draft.is_changed() {
+        return;
+    }
+    for mut text in q_text.iter_mut() {
+        text.sections[0].value = draft.amount.to_string();
+    }
+}
+
+// gold already on the way is dropped where it is
+fn cancel_order(
+    commands: &mut Commands,
+    order_ent: Entity,
+    q_couriers: &Query<(Entity, &Courier)>,
+) {
+    for (gold_ent, courier) in q_couriers.iter() {
+        if courier.order == order_ent {
+            commands.entity(gold_ent).remove::<Courier>();
+        }
+    }
+    commands.entity(order_ent).despawn_recursive();
+}
+
+fn run_transfers(
+    mut commands: Commands,
+    mut q_orders: Query<(Entity, &mut TransferOrder)>,
+    mut q_piles: Query<(&Transform, &mut GoldPile)>,
+    asset_server: Res<AssetServer>,
+    decay: Res<GoldDecaySettings>,
+    time: Res<Time>,
+) {
+    for (ent, mut order) in q_orders.iter_mut() {
+        if !order.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        // destination gone means nothing to do
+        if !q_piles.contains(order.dest) {
+            commands.entity(ent).despawn_recursive();
+            continue;
+        }
+
+        let source = order.source;
+        match q_piles.get_mut(source) {
+            Ok((trans, mut pile)) => {
+                if pile.count == 0 || order.remaining == 0 {
+                    commands.entity(ent).despawn_recursive();
+                    continue;
+                }
+                pile.count -= 1;
+                order.remaining -= 1;
+
+                let gold = spawn_gold_bundle(
+                    &mut commands,
+                    &asset_server,
+                    trans.translation,
+                    decay.lifetime,
+                );
+                commands
+                    .entity(gold)
+                    .insert(Withdrawn { pile: source })
+                    .insert(Banked)
+                    .insert(Courier {
+                        order: ent,
+                        dest: order.dest,
+                    });
+            }
+            Err(_) => {
+                commands.entity(ent).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn move_couriers(
+    mut commands: Commands,
+    mut q_gold: Query<(Entity, &mut Transform, &Courier), With<Gold>>,
+    q_piles: Query<(&Transform, &GoldPile), Without<Gold>>,
+    time: Res<Time>,
+) {
+    for (ent, mut trans, courier) in q_gold.iter_mut() {
+        match q_piles.get(courier.dest) {
+            Ok((pile_trans, pile)) => {
+                let dir = pile_trans.translation.truncate() - trans.translation.truncate();
+                let step = COURIER_SPEED * time.delta_seconds();
+                if dir.length() <= step {
+                    // store_gold takes it from here
+                    // unless the pile is full
+                    if pile.count >= pile.gold_cap {
+                        commands.entity(ent).remove::<Courier>();
+                    }
+                } else {
+                    trans.translation += dir.normalize_or_zero().extend(0.0) * step;
+                }
+            }
+            Err(_) => {
+                commands.entity(ent).remove::<Courier>();
+            }
+        }
+    }
+}
+
+fn reset_transfers(
+    mut commands: Commands,
+    mut ev_restart: EventReader<RestartEvent>,
+    mut draft: ResMut<TransferDraft>,
+    q_orders: Query<Entity, Or<(With<TransferOrder>, With<SourceMarker>)>>,
+) {
+    for _ev in ev_restart.iter() {
+        draft.source = None;
+        draft.amount = TRANSFER_DEFAULT_AMOUNT;
+        for ent in q_orders.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
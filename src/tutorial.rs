@@ -1,6 +1,11 @@
 use bevy::prelude::*;
 
-use crate::{enemies::BossCapEvent, StartSpawningEnemiesEvent};
+use crate::{
+    boss::BossCapEvent,
+    difficulty::{Difficulty, DifficultyButton, Preset},
+    stats::RunStats,
+    GameOverEvent, LossReason, RestartEvent, StartSpawningEnemiesEvent,
+};
 
 pub struct TutorialPlugin;
 
@@ -12,7 +17,9 @@ impl Plugin for TutorialPlugin {
             .add_system(button_system)
             .add_system(remove_start_menu)
             .add_system(allow_input)
-            .add_system(win_menu);
+            .add_system(show_difficulty)
+            .add_system(win_menu)
+            .add_system(lose_menu);
     }
 }
 
@@ -22,15 +29,27 @@ struct StartMenu;
 #[derive(Component)]
 struct RemoveButton;
 
+// text shown on the button's first child
 #[derive(Component)]
-struct ButtonInfo {
-    base_text: String,
-    hovered_text: String,
+pub struct ButtonInfo {
+    pub base_text: String,
+    pub hovered_text: String,
 }
 
+// clicking it doesn't close the start or end menus
+#[derive(Component)]
+pub struct KeepMenu;
+
+// shows the picked difficulty on the start menu
+#[derive(Component)]
+struct DifficultyLabel;
+
 #[derive(Component)]
 struct EndMenu;
 
+#[derive(Component)]
+struct RestartButton;
+
 struct RemoveMenuEvent;
 
 pub struct AcceptInput(pub bool);
@@ -41,15 +60,23 @@ const PRESSED_BUTTOM: Color = Color::rgb(0.35, 0.75, 0.35);
 
 fn button_system(
     mut q_interaction: Query<
-        (&Interaction, &mut UiColor, &Children, Option<&RemoveButton>),
+        (
+            &Interaction,
+            &mut UiColor,
+            &Children,
+            Option<&RemoveButton>,
+            Option<&RestartButton>,
+            Option<&KeepMenu>,
+        ),
         (Changed<Interaction>, With<Button>),
     >,
     q_child: Query<&ButtonInfo>,
     mut q_text: Query<&mut Text>,
     mut ev_start: EventWriter<StartSpawningEnemiesEvent>,
     mut ev_remove: EventWriter<RemoveMenuEvent>,
+    mut ev_restart: EventWriter<RestartEvent>,
 ) {
-    for (interaction, mut color, children, start) in &mut q_interaction {
+    for (interaction, mut color, children, start, restart, keep) in &mut q_interaction {
         let mut text = q_text.get_mut(children[0]).unwrap();
         let info = q_child.get(children[0]);
         match *interaction {
@@ -61,7 +88,14 @@ fn button_system(
                     ev_start.send(StartSpawningEnemiesEvent);
                     // don't let the player click through the menu
                 }
-                ev_remove.send(RemoveMenuEvent);
+                if let Some(_) = restart {
+                    ev_restart.send(RestartEvent);
+                    ev_start.send(StartSpawningEnemiesEvent);
+                }
+                // settings and build buttons leave the menus up
+                if keep.is_none() {
+                    ev_remove.send(RemoveMenuEvent);
+                }
             }
             Interaction::Hovered => {
                 text.sections[0].value = info.unwrap().hovered_text.clone();
@@ -77,11 +111,15 @@ fn button_system(
 
 fn allow_input(
     mut ev_start: EventReader<StartSpawningEnemiesEvent>,
+    mut ev_game_over: EventReader<GameOverEvent>,
     mut accept: ResMut<AcceptInput>,
 ) {
     for _ev in ev_start.iter() {
         accept.0 = true;
     }
+    for _ev in ev_game_over.iter() {
+        accept.0 = false;
+    }
 }
 
 fn start_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
@@ -113,6 +151,66 @@ fn start_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                 ..default()
             })
             .with_children(|center| {
+                // column goes bottom up, so these end up under the start button
+                center
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(60.0), Val::Percent(8.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        spawn_difficulty_button(
+                            row,
+                            &asset_server,
+                            DifficultyButton::Preset(Preset::Easy),
+                            "Easy",
+                        );
+                        spawn_difficulty_button(
+                            row,
+                            &asset_server,
+                            DifficultyButton::Preset(Preset::Normal),
+                            "Normal",
+                        );
+                        spawn_difficulty_button(
+                            row,
+                            &asset_server,
+                            DifficultyButton::Preset(Preset::Hard),
+                            "Hard",
+                        );
+                        spawn_difficulty_button(
+                            row,
+                            &asset_server,
+                            DifficultyButton::Preset(Preset::Custom),
+                            "Custom",
+                        );
+                        spawn_difficulty_button(
+                            row,
+                            &asset_server,
+                            DifficultyButton::Adaptive,
+                            "Adaptive",
+                        );
+                    });
+                center
+                    .spawn_bundle(
+                        TextBundle::from_section(
+                            "Normal",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 30.0,
+                                color: Color::rgb(0.15, 0.15, 0.15),
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        }),
+                    )
+                    .insert(DifficultyLabel);
                 center
                     .spawn_bundle(ButtonBundle {
                         style: Style {
@@ -164,6 +262,58 @@ fn start_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
         });
 }
 
+fn spawn_difficulty_button(
+    row: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    button: DifficultyButton,
+    text: &str,
+) {
+    row.spawn_bundle(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(110.0), Val::Px(40.0)),
+            margin: UiRect::all(Val::Px(5.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: NORMAL_BUTTOM.into(),
+        ..default()
+    })
+    .insert(button)
+    .insert(KeepMenu)
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 24.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ))
+            .insert(ButtonInfo {
+                base_text: text.to_string(),
+                hovered_text: text.to_string(),
+            });
+    });
+}
+
+fn show_difficulty(
+    difficulty: Res<Difficulty>,
+    mut q_label: Query<&mut Text, With<DifficultyLabel>>,
+) {
+    if !difficulty.is_changed() {
+        return;
+    }
+    for mut text in q_label.iter_mut() {
+        text.sections[0].value = if difficulty.adaptive {
+            format!("{:?} (adaptive)", difficulty.preset)
+        } else {
+            format!("{:?}", difficulty.preset)
+        };
+    }
+}
+
 fn remove_start_menu(
     mut commands: Commands,
     mut ev_remove: EventReader<RemoveMenuEvent>,
@@ -181,84 +331,193 @@ fn win_menu(
     mut ev_boss: EventReader<BossCapEvent>,
     asset_server: Res<AssetServer>,
 ) {
-    for _ev in ev_boss.iter() {
+    for ev in ev_boss.iter() {
+        // the others just pay out
+        if ev.final_boss {
+            spawn_end_menu(&mut commands, &asset_server, "You Win", "Continue");
+        }
+    }
+}
+
+fn lose_menu(
+    mut commands: Commands,
+    mut ev_game_over: EventReader<GameOverEvent>,
+    q_menu: Query<Entity, With<EndMenu>>,
+    stats: Res<RunStats>,
+    asset_server: Res<AssetServer>,
+) {
+    // one screen even if a few came in at once
+    if let Some(ev) = ev_game_over.iter().last() {
+        for ent in q_menu.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+
+        let reason = match ev.reason {
+            LossReason::CenterPileEmptied => "The center pile was emptied".to_string(),
+            LossReason::TooMuchStolen => format!("{} gold was stolen", stats.gold_stolen),
+            LossReason::OutOfLives => format!("{} thieves got away", stats.escaped),
+        };
+        let lines = [
+            reason,
+            format!("Gold banked: {}", stats.gold_banked),
+            format!("Gold stolen: {}", stats.gold_stolen),
+            format!("Enemies killed: {}", stats.enemies_killed),
+            format!("Waves cleared: {}", stats.waves_cleared),
+            format!(
+                "Time: {}:{:02}",
+                stats.time as u32 / 60,
+                stats.time as u32 % 60
+            ),
+        ];
+        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
         commands
             .spawn_bundle(NodeBundle {
                 style: Style {
                     size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
                     justify_content: JustifyContent::Center,
-                    align_content: AlignContent::Center,
                     align_items: AlignItems::Center,
+                    // column is laid out bottom up
+                    flex_direction: FlexDirection::ColumnReverse,
                     ..default()
                 },
-                color: Color::NONE.into(),
+                color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
                 ..default()
             })
             .insert(EndMenu)
-            .with_children(|root| {
-                root.spawn_bundle(NodeBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                        justify_content: JustifyContent::Center,
-                        //align_content: AlignContent::Center,
-                        align_items: AlignItems::Center,
-                        flex_direction: FlexDirection::Column,
-
-                        ..default()
+            .with_children(|center| {
+                center.spawn_bundle(TextBundle::from_section(
+                    "Game Over",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 60.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
                     },
-                    color: Color::NONE.into(), //:BEIGE.into(),
-                    ..default()
-                })
-                .with_children(|center| {
-                    center
-                        .spawn_bundle(ButtonBundle {
-                            style: Style {
-                                // default is 1280x720
-                                // 150/1280 = 11.7%
-                                // 65/720 = 9%
-                                // 12% of 40% = 30%
-                                //size: Size::new(Val::Px(150.0), Val::Px(65.0)),
-                                size: Size::new(Val::Percent(10.0), Val::Percent(10.0)),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            color: NORMAL_BUTTOM.into(),
+                ));
+                for line in lines {
+                    center.spawn_bundle(TextBundle::from_section(
+                        line,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 28.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                }
+                center
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(12.0), Val::Percent(10.0)),
+                            margin: UiRect::all(Val::Px(20.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
                             ..default()
-                        })
-                        //.insert(RemoveButton)
-                        .with_children(|parent| {
-                            parent
-                                .spawn_bundle(TextBundle::from_section(
-                                    "Button",
-                                    TextStyle {
-                                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                        font_size: 40.0,
-                                        color: Color::rgb(0.9, 0.9, 0.9),
-                                    },
-                                ))
-                                .insert(ButtonInfo {
-                                    base_text: "You Win".to_string(),
-                                    hovered_text: "Continue".to_string(),
-                                });
-                        });
-                    center.spawn_bundle(NodeBundle {
+                        },
+                        color: NORMAL_BUTTOM.into(),
+                        ..default()
+                    })
+                    .insert(RestartButton)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle::from_section(
+                                "Button",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 40.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ))
+                            .insert(ButtonInfo {
+                                base_text: "Restart".to_string(),
+                                hovered_text: "Again".to_string(),
+                            });
+                    });
+            });
+    }
+}
+
+fn spawn_end_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    base_text: &str,
+    hovered_text: &str,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_content: AlignContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(EndMenu)
+        .with_children(|root| {
+            root.spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    //align_content: AlignContent::Center,
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Column,
+
+                    ..default()
+                },
+                color: Color::NONE.into(), //:BEIGE.into(),
+                ..default()
+            })
+            .with_children(|center| {
+                center
+                    .spawn_bundle(ButtonBundle {
                         style: Style {
-                            //size: Size::new(Val::Percent(100.0), Val::Percent(30.0)),
-                            size: Size::new(Val::Px(500.0), Val::Px(76.0)),
-                            
-                            margin: UiRect::new(
-                                Val::Auto,
-                                Val::Auto,
-                                Val::Percent(10.0),
-                                Val::Percent(10.0),
-                            ),
+                            // default is 1280x720
+                            // 150/1280 = 11.7%
+                            // 65/720 = 9%
+                            // 12% of 40% = 30%
+                            //size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                            size: Size::new(Val::Percent(10.0), Val::Percent(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
                             ..default()
                         },
-                        image: UiImage(asset_server.load("sprites/Title.png")),
+                        color: NORMAL_BUTTOM.into(),
                         ..default()
+                    })
+                    //.insert(RemoveButton)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle::from_section(
+                                "Button",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 40.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ))
+                            .insert(ButtonInfo {
+                                base_text: base_text.to_string(),
+                                hovered_text: hovered_text.to_string(),
+                            });
                     });
+                center.spawn_bundle(NodeBundle {
+                    style: Style {
+                        //size: Size::new(Val::Percent(100.0), Val::Percent(30.0)),
+                        size: Size::new(Val::Px(500.0), Val::Px(76.0)),
+
+                        margin: UiRect::new(
+                            Val::Auto,
+                            Val::Auto,
+                            Val::Percent(10.0),
+                            Val::Percent(10.0),
+                        ),
+                        ..default()
+                    },
+                    image: UiImage(asset_server.load("sprites/Title.png")),
+                    ..default()
                 });
             });
-    }
+        });
 }
@@ -0,0 +1,418 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::f32::consts::{FRAC_PI_4, TAU};
+
+use crate::difficulty::Difficulty;
+use crate::enemies::{Dead, Enemy, EnemyKind, SpawnEnemyEvent};
+use crate::indicators::{spawn_telegraph, SpawnTelegraph};
+use crate::{GameOverEvent, StartSpawningEnemiesEvent};
+
+const SPAWN_RADIUS: f32 = 500.0;
+// after the last wave in the file
+const ENDLESS_DELAY: f32 = 10.0;
+const ENDLESS_GROWTH: u32 = 2;
+const ENDLESS_MAX_SPEED: f32 = 2.0;
+// spawn points show up this long before the wave
+const TELEGRAPH_TIME: f32 = 3.0;
+// spawns this close together share a marker
+const TELEGRAPH_ANGLE: f32 = 10.0;
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WaveStartedEvent>()
+            .add_event::<WaveClearedEvent>()
+            .insert_resource(WaveList(parse_waves(include_str!(
+                "../assets/data/waves.txt"
+            ))))
+            .add_system(setup)
+            .add_system(run_spawner)
+            .add_system(check_cleared)
+            .add_system(stop_spawner);
+    }
+}
+
+pub struct WaveStartedEvent {
+    pub wave: u32,
+}
+
+// everything from the wave has spawned and is gone
+pub struct WaveClearedEvent {
+    pub wave: u32,
+}
+
+// which wave an enemy came from
+#[derive(Component)]
+pub struct WaveTag(pub u32);
+
+#[derive(Clone)]
+enum SpawnArea {
+    // anywhere on the circle
+    All,
+    // a quarter of the circle around this angle
+    Edge(f32),
+    Point(Vec2),
+}
+
+#[derive(Clone)]
+pub struct WaveDef {
+    delay: f32,
+    spacing: f32,
+    areas: Vec<SpawnArea>,
+    // (kind, count)
+    groups: Vec<(EnemyKind, u32)>,
+    speed: f32,
+}
+
+impl Default for WaveDef {
+    fn default() -> Self {
+        WaveDef {
+            delay: ENDLESS_DELAY,
+            spacing: 0.0,
+            areas: vec![SpawnArea::All],
+            groups: Vec::new(),
+            speed: 1.0,
+        }
+    }
+}
+
+struct WaveList(Vec<WaveDef>);
+
+#[derive(Component)]
+struct EnemySpawner {
+    // the wave being waited on or spawned
+    wave: u32,
+    def: WaveDef,
+    delay: Timer,
+    spacing: Timer,
+    spawning: bool,
+    // rolled when the wave is telegraphed
+    queue: Vec<SpawnEnemyEvent>,
+    telegraphed: bool,
+    // kept here so they can go the same frame they're made
+    telegraphs: Vec<Entity>,
+    // (wave, finished spawning, seen an enemy from it)
+    open: Vec<(u32, bool, bool)>,
+}
+
+impl EnemySpawner {
+    fn new(list: &WaveList, difficulty: &Difficulty) -> Self {
+        let def = wave_def(list, 1, difficulty);
+        EnemySpawner {
+            wave: 1,
+            delay: Timer::from_seconds(def.delay.max(0.01), false),
+            spacing: Timer::from_seconds(def.spacing.max(0.01), true),
+            spawning: false,
+            queue: Vec::new(),
+            telegraphed: false,
+            telegraphs: Vec::new(),
+            def,
+            open: Vec::new(),
+        }
+    }
+
+    fn queue_next(&mut self, list: &WaveList, difficulty: &Difficulty) {
+        self.wave += 1;
+        self.def = wave_def(list, self.wave, difficulty);
+        self.delay = Timer::from_seconds(self.def.delay.max(0.01), false);
+        self.spacing = Timer::from_seconds(self.def.spacing.max(0.01), true);
+        self.spawning = false;
+        self.queue.clear();
+        self.telegraphed = false;
+    }
+
+    fn time_left(&self) -> f32 {
+        self.delay.duration().as_secs_f32() - self.delay.elapsed_secs()
+    }
+}
+
+fn parse_waves(text: &str) -> Vec<WaveDef> {
+    let mut waves = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_no = i + 1;
+
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("wave") {
+            warn!("waves.txt line {}: expected a wave", line_no);
+            continue;
+        }
+
+        let mut wave = WaveDef::default();
+        for token in tokens {
+            let (key, value) = match token.split_once('=') {
+                Some(kv) => kv,
+                None => {
+                    warn!("waves.txt line {}: can't read {}", line_no, token);
+                    continue;
+                }
+            };
+            match key {
+                "delay" => wave.delay = parse_number(value, wave.delay, line_no),
+                "spacing" => wave.spacing = parse_number(value, wave.spacing, line_no),
+                "speed" => wave.speed = parse_number(value, wave.speed, line_no),
+                "spawn" => {
+                    wave.areas = value
+                        .split(',')
+                        .filter_map(|area| parse_area(area, line_no))
+                        .collect();
+                    if wave.areas.is_empty() {
+                        wave.areas.push(SpawnArea::All);
+                    }
+                }
+                "enemies" => {
+                    wave.groups = value
+                        .split(',')
+                        .filter_map(|group| parse_group(group, line_no))
+                        .collect();
+                }
+                _ => warn!("waves.txt line {}: unknown setting {}", line_no, key),
+            }
+        }
+        waves.push(wave);
+    }
+    waves
+}
+
+fn parse_number(value: &str, default: f32, line_no: usize) -> f32 {
+    value.parse().unwrap_or_else(|_| {
+        warn!("waves.txt line {}: {} isn't a number", line_no, value);
+        default
+    })
+}
+
+fn parse_area(area: &str, line_no: usize) -> Option<SpawnArea> {
+    let deg = match area {
+        "all" => return Some(SpawnArea::All),
+        "east" => 0.0_f32,
+        "north" => 90.0,
+        "west" => 180.0,
+        "south" => 270.0,
+        _ => {
+            let mut parts = area.split(':');
+            if parts.next() == Some("at") {
+                let x = parts.next().and_then(|x| x.parse().ok());
+                let y = parts.next().and_then(|y| y.parse().ok());
+                if let (Some(x), Some(y)) = (x, y) {
+                    return Some(SpawnArea::Point(Vec2::new(x, y)));
+                }
+            }
+            warn!("waves.txt line {}: unknown spawn {}", line_no, area);
+            return None;
+        }
+    };
+    Some(SpawnArea::Edge(deg.to_radians()))
+}
+
+fn parse_group(group: &str, line_no: usize) -> Option<(EnemyKind, u32)> {
+    let (kind, count) = match group.split_once('*') {
+        Some((kind, count)) => match count.parse() {
+            Ok(count) => (kind, count),
+            Err(_) => {
+                warn!("waves.txt line {}: {} isn't a count", line_no, count);
+                return None;
+            }
+        },
+        None => (group, 1),
+    };
+    match EnemyKind::from_name(kind) {
+        Some(kind) => Some((kind, count)),
+        None => {
+            warn!("waves.txt line {}: unknown enemy {}", line_no, kind);
+            None
+        }
+    }
+}
+
+// waves count from 1
+// past the end of the file it keeps growing the last wave
+fn wave_def(list: &WaveList, wave: u32, difficulty: &Difficulty) -> WaveDef {
+    let mut def = endless_def(list, wave);
+    def.delay *= difficulty.spawn_delay;
+    def.spacing *= difficulty.spawn_delay;
+    def
+}
+
+fn endless_def(list: &WaveList, wave: u32) -> WaveDef {
+    if let Some(def) = list.0.get(wave as usize - 1) {
+        return def.clone();
+    }
+
+    let extra = wave - list.0.len() as u32;
+    let mut def = list.0.last().cloned().unwrap_or_default();
+    if def.groups.is_empty() {
+        def.groups.push((EnemyKind::Grunt, 10));
+    }
+    def.delay = ENDLESS_DELAY;
+    for group in def.groups.iter_mut() {
+        group.1 += ENDLESS_GROWTH * extra;
+    }
+    def.speed = (def.speed * (1.0 + 0.02 * extra as f32)).min(ENDLESS_MAX_SPEED);
+    def
+}
+
+// pick where everything in the wave will spawn
+// scale grows or shrinks every group, but never to nothing
+fn roll_wave(def: &WaveDef, wave: u32, scale: f32) -> Vec<SpawnEnemyEvent> {
+    let mut rng = rand::thread_rng();
+    let mut queue = Vec::new();
+    for &(kind, count) in def.groups.iter() {
+        let count = if count > 0 {
+            ((count as f32 * scale).round() as u32).max(1)
+        } else {
+            0
+        };
+        for _ in 0..count {
+            let pos = match def.areas.choose(&mut rng).unwrap_or(&SpawnArea::All) {
+                SpawnArea::All => {
+                    let angle = rng.gen_range(0.0..TAU);
+                    Vec2::new(angle.cos(), angle.sin()) * SPAWN_RADIUS
+                }
+                SpawnArea::Edge(center) => {
+                    let angle = center + rng.gen_range(-FRAC_PI_4..FRAC_PI_4);
+                    Vec2::new(angle.cos(), angle.sin()) * SPAWN_RADIUS
+                }
+                SpawnArea::Point(point) => *point,
+            };
+            queue.push(SpawnEnemyEvent {
+                kind,
+                position: pos.extend(0.3),
+                speed: def.speed,
+                wave,
+            });
+        }
+    }
+    // mix the groups together
+    queue.shuffle(&mut rng);
+    queue
+}
+
+fn setup(
+    mut commands: Commands,
+    mut ev_start: EventReader<StartSpawningEnemiesEvent>,
+    q_spawner: Query<Entity, Or<(With<EnemySpawner>, With<SpawnTelegraph>)>>,
+    waves: Res<WaveList>,
+    difficulty: Res<Difficulty>,
+) {
+    for _ev in ev_start.iter() {
+        // a restart starts again from wave 1
+        for ent in q_spawner.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+        commands
+            .spawn()
+            .insert(EnemySpawner::new(&waves, &difficulty));
+    }
+}
+
+// one marker for each bunch of spawn points
+fn telegraph_wave(commands: &mut Commands, queue: &[SpawnEnemyEvent]) -> Vec<Entity> {
+    let mut groups: Vec<(i32, Vec2, usize)> = Vec::new();
+    for ev in queue.iter() {
+        let pos = ev.position.truncate();
+        let bucket = (pos.y.atan2(pos.x).to_degrees() / TELEGRAPH_ANGLE).round() as i32;
+        match groups.iter_mut().find(|(b, _, _)| *b == bucket) {
+            Some(group) => {
+                group.1 += pos;
+                group.2 += 1;
+            }
+            None => groups.push((bucket, pos, 1)),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, total, count)| spawn_telegraph(commands, total / count as f32, count))
+        .collect()
+}
+
+fn run_spawner(
+    mut commands: Commands,
+    mut q_spawner: Query<&mut EnemySpawner>,
+    waves: Res<WaveList>,
+    difficulty: Res<Difficulty>,
+    mut ev_spawn_enemy: EventWriter<SpawnEnemyEvent>,
+    mut ev_wave: EventWriter<WaveStartedEvent>,
+    time: Res<Time>,
+) {
+    for mut spawner in q_spawner.iter_mut() {
+        if !spawner.spawning {
+            spawner.delay.tick(time.delta());
+            if !spawner.telegraphed && spawner.time_left() <= TELEGRAPH_TIME {
+                spawner.telegraphed = true;
+                // rolled late so adaptive changes from the last wave count
+                spawner.queue = roll_wave(&spawner.def, spawner.wave, difficulty.wave_size());
+                spawner.telegraphs = telegraph_wave(&mut commands, &spawner.queue);
+            }
+            if spawner.delay.just_finished() {
+                spawner.spawning = true;
+                for ent in spawner.telegraphs.drain(..) {
+                    commands.entity(ent).despawn_recursive();
+                }
+                let wave = spawner.wave;
+                // nothing to wait for in an empty wave
+                let empty = spawner.queue.is_empty();
+                spawner.open.push((wave, false, empty));
+                ev_wave.send(WaveStartedEvent { wave });
+            }
+            continue;
+        }
+
+        if spawner.def.spacing <= 0.0 {
+            for ev in spawner.queue.drain(..) {
+                ev_spawn_enemy.send(ev);
+            }
+        } else if spawner.spacing.tick(time.delta()).just_finished() {
+            if let Some(ev) = spawner.queue.pop() {
+                ev_spawn_enemy.send(ev);
+            }
+        }
+
+        if spawner.queue.is_empty() {
+            let wave = spawner.wave;
+            for open in spawner.open.iter_mut() {
+                if open.0 == wave {
+                    open.1 = true;
+                }
+            }
+            spawner.queue_next(&waves, &difficulty);
+        }
+    }
+}
+
+fn check_cleared(
+    mut q_spawner: Query<&mut EnemySpawner>,
+    q_enemies: Query<&WaveTag, (With<Enemy>, Without<Dead>)>,
+    mut ev_cleared: EventWriter<WaveClearedEvent>,
+) {
+    for mut spawner in q_spawner.iter_mut() {
+        for open in spawner.open.iter_mut() {
+            if q_enemies.iter().any(|tag| tag.0 == open.0) {
+                open.2 = true;
+            }
+        }
+        spawner.open.retain(|&(wave, done, seen)| {
+            if done && seen && !q_enemies.iter().any(|tag| tag.0 == wave) {
+                ev_cleared.send(WaveClearedEvent { wave });
+                return false;
+            }
+            true
+        });
+    }
+}
+
+// nothing more comes once the run is over
+fn stop_spawner(
+    mut commands: Commands,
+    mut ev_game_over: EventReader<GameOverEvent>,
+    q_spawner: Query<Entity, Or<(With<EnemySpawner>, With<SpawnTelegraph>)>>,
+) {
+    for _ev in ev_game_over.iter() {
+        for ent in q_spawner.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}